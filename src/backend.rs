@@ -0,0 +1,613 @@
+use anyhow::{anyhow, bail, Result};
+use mpris::{FindingError, Metadata, PlaybackStatus, Player, PlayerFinder, TrackID};
+use log::{debug, error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// How long to wait before trying to reach the session bus again
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A player found during discovery.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlayerEntry {
+    /// Unique, stable identifier (the D-Bus bus name for MPRIS players)
+    pub id: String,
+    /// Human readable player name, e.g. "Spotify"
+    pub identity: String,
+}
+
+/// Playback speed of a player that supports changing it, as factors of normal speed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct PlaybackRate {
+    pub current: f64,
+    pub minimum: f64,
+    pub maximum: f64,
+}
+
+/// What a player allows to be done to it (the MPRIS `Can*` properties).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Capabilities {
+    pub can_control: bool,
+    pub can_play: bool,
+    pub can_pause: bool,
+    pub can_go_next: bool,
+    pub can_go_previous: bool,
+    pub can_seek: bool,
+    /// The player window can be brought to the front
+    pub can_raise: bool,
+    pub can_quit: bool,
+    /// The track can be rated through the backend
+    pub can_rate: bool,
+}
+
+impl Default for Capabilities {
+    /// Assume everything works until the player says otherwise
+    fn default() -> Self {
+        Self {
+            can_control: true,
+            can_play: true,
+            can_pause: true,
+            can_go_next: true,
+            can_go_previous: true,
+            can_seek: true,
+            can_raise: true,
+            can_quit: true,
+            // MPRIS only exposes `xesam:userRating`, it cannot change it
+            can_rate: false,
+        }
+    }
+}
+
+impl Capabilities {
+    /// Whether the player accepts `command`
+    pub fn allows(&self, command: &PlayerCommand) -> bool {
+        match command {
+            // The root interface, which CanControl does not cover
+            PlayerCommand::Raise => self.can_raise,
+            PlayerCommand::Quit => self.can_quit,
+            PlayerCommand::PlayPause | PlayerCommand::Pause => self.can_control && self.can_pause,
+            PlayerCommand::Play => self.can_control && self.can_play,
+            PlayerCommand::Stop
+            | PlayerCommand::SetRate(_)
+            | PlayerCommand::OpenUri(_)
+            | PlayerCommand::SetVolume(_) => self.can_control,
+            PlayerCommand::Next => self.can_control && self.can_go_next,
+            PlayerCommand::Previous => self.can_control && self.can_go_previous,
+            PlayerCommand::SetPosition { .. } => self.can_control && self.can_seek,
+            PlayerCommand::SetRating(_) => self.can_control && self.can_rate,
+        }
+    }
+}
+
+/// Returned (through `anyhow`) when the D-Bus session bus cannot be reached,
+/// e.g. over SSH, in a container or on a bare TTY.
+#[derive(Debug, Clone)]
+pub struct SessionBusUnavailable {
+    pub reason: String,
+}
+
+impl fmt::Display for SessionBusUnavailable {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "D-Bus session bus unavailable: {}", self.reason)
+    }
+}
+
+impl std::error::Error for SessionBusUnavailable {}
+
+/// Commands that can be sent to a player.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlayerCommand {
+    PlayPause,
+    Play,
+    Pause,
+    Stop,
+    Next,
+    Previous,
+    /// Jump to `position` in the track with the given `mpris:trackid`
+    SetPosition { track_id: String, position: Duration },
+    /// Playback speed as a factor, within the player's `PlaybackRate` limits
+    SetRate(f64),
+    /// Bring the player window to the front
+    Raise,
+    /// Close the player itself
+    Quit,
+    /// Open (or enqueue) a URI the player supports
+    OpenUri(String),
+    /// Volume from 0.0 to 1.0
+    SetVolume(f64),
+    /// Rating of the current track from 0.0 to 1.0, like `xesam:userRating`
+    SetRating(f64),
+}
+
+/// Everything `MusicPlayer` needs from a source of media players.
+///
+/// Players are addressed by the `id` returned from `find_all`; looking up a
+/// player that has gone away is an error.
+pub trait PlayerBackend {
+    fn find_all(&mut self) -> Result<Vec<PlayerEntry>>;
+    fn playback_status(&self, id: &str) -> Result<PlaybackStatus>;
+    fn metadata(&self, id: &str) -> Result<Metadata>;
+    fn position(&self, id: &str) -> Result<Duration>;
+    /// `None` if the player does not support changing the playback speed
+    fn rate(&self, id: &str) -> Result<Option<PlaybackRate>>;
+    /// 0.0 to 1.0, `None` if the player has no volume control
+    fn volume(&self, id: &str) -> Result<Option<f64>>;
+    fn capabilities(&self, id: &str) -> Result<Capabilities>;
+    /// URI schemes `OpenUri` accepts, e.g. "file" and "https"
+    fn supported_uri_schemes(&self, id: &str) -> Result<Vec<String>>;
+    fn send(&mut self, id: &str, command: &PlayerCommand) -> Result<()>;
+}
+
+/// Backend talking to MPRIS players over the D-Bus session bus.
+///
+/// The bus connection is opened lazily and dropped again when it breaks, so
+/// the backend recovers once a session bus becomes reachable.
+#[derive(Default)]
+pub struct MprisBackend {
+    finder: Option<PlayerFinder>,
+    players: HashMap<String, Player>,
+    /// Connection for the calls the mpris crate does not wrap, which keeps
+    /// its own connection private; opened on first use
+    connection: Option<dbus::blocking::Connection>,
+    last_attempt: Option<Instant>,
+    last_error: Option<SessionBusUnavailable>,
+}
+
+impl MprisBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn connect(&mut self) -> Result<&PlayerFinder> {
+        if self.finder.is_none() {
+            if let (Some(at), Some(err)) = (self.last_attempt, &self.last_error) {
+                if at.elapsed() < RECONNECT_INTERVAL {
+                    return Err(err.clone().into());
+                }
+            }
+
+            self.last_attempt = Some(Instant::now());
+            match PlayerFinder::new() {
+                Ok(finder) => {
+                    info!("Connected to D-Bus session bus");
+                    self.finder = Some(finder);
+                    self.last_error = None;
+                }
+                Err(e) => {
+                    let err = SessionBusUnavailable { reason: e.to_string() };
+                    self.last_error = Some(err.clone());
+                    return Err(err.into());
+                }
+            }
+        }
+
+        Ok(self.finder.as_ref().unwrap())
+    }
+
+    /// `OpenUri` on the player's bus name; the mpris crate does not wrap it
+    fn open_uri(&mut self, bus_name: &str, uri: &str) -> Result<()> {
+        if self.connection.is_none() {
+            self.connection = Some(dbus::blocking::Connection::new_session()?);
+        }
+        let connection = self.connection.as_ref().unwrap();
+        let proxy = connection.with_proxy(bus_name, "/org/mpris/MediaPlayer2", Duration::from_secs(5));
+        let result = proxy.method_call::<(), _, _, _>("org.mpris.MediaPlayer2.Player", "OpenUri", (uri,));
+        if let Err(e) = &result {
+            // Most likely the player's own error, but the connection may be gone too
+            if e.name() == Some("org.freedesktop.DBus.Error.Disconnected") {
+                self.connection = None;
+            }
+        }
+        Ok(result?)
+    }
+
+    fn player(&self, id: &str) -> Result<&Player> {
+        self.players
+            .get(id)
+            .ok_or_else(|| anyhow!("Player {} is no longer available", id))
+    }
+}
+
+impl PlayerBackend for MprisBackend {
+    fn find_all(&mut self) -> Result<Vec<PlayerEntry>> {
+        let players = match self.connect()?.find_all() {
+            Ok(players) => players,
+            Err(FindingError::DBusError(e)) => {
+                // The connection is most likely gone; reconnect on the next call
+                error!("Lost D-Bus session bus connection: {}", e);
+                self.finder = None;
+                self.connection = None;
+                self.players.clear();
+                let err = SessionBusUnavailable { reason: e.to_string() };
+                self.last_error = Some(err.clone());
+                return Err(err.into());
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        self.players.clear();
+        let mut entries = Vec::with_capacity(players.len());
+        for player in players {
+            let entry = PlayerEntry {
+                id: player.bus_name().to_string(),
+                identity: player.identity().to_string(),
+            };
+            self.players.insert(entry.id.clone(), player);
+            entries.push(entry);
+        }
+
+        Ok(entries)
+    }
+
+    fn playback_status(&self, id: &str) -> Result<PlaybackStatus> {
+        Ok(self.player(id)?.get_playback_status()?)
+    }
+
+    fn metadata(&self, id: &str) -> Result<Metadata> {
+        Ok(self.player(id)?.get_metadata()?)
+    }
+
+    fn position(&self, id: &str) -> Result<Duration> {
+        Ok(self.player(id)?.get_position()?)
+    }
+
+    fn rate(&self, id: &str) -> Result<Option<PlaybackRate>> {
+        let player = self.player(id)?;
+        let Some(current) = player.checked_get_playback_rate()? else {
+            return Ok(None);
+        };
+        let range = player.get_valid_playback_rate_range()?;
+        // A fixed rate cannot be changed
+        if range.start >= range.end {
+            return Ok(None);
+        }
+        Ok(Some(PlaybackRate {
+            current,
+            minimum: range.start,
+            maximum: range.end,
+        }))
+    }
+
+    fn volume(&self, id: &str) -> Result<Option<f64>> {
+        Ok(self.player(id)?.checked_get_volume()?)
+    }
+
+    fn capabilities(&self, id: &str) -> Result<Capabilities> {
+        let player = self.player(id)?;
+        Ok(Capabilities {
+            can_control: player.can_control()?,
+            can_play: player.can_play()?,
+            can_pause: player.can_pause()?,
+            can_go_next: player.can_go_next()?,
+            can_go_previous: player.can_go_previous()?,
+            can_seek: player.can_seek()?,
+            can_raise: player.can_raise()?,
+            can_quit: player.can_quit()?,
+            can_rate: false,
+        })
+    }
+
+    fn supported_uri_schemes(&self, id: &str) -> Result<Vec<String>> {
+        Ok(self.player(id)?.get_supported_uri_schemes()?)
+    }
+
+    fn send(&mut self, id: &str, command: &PlayerCommand) -> Result<()> {
+        if let PlayerCommand::OpenUri(uri) = command {
+            self.player(id)?;
+            return self.open_uri(id, uri);
+        }
+        let player = self.player(id)?;
+        match command {
+            PlayerCommand::PlayPause => player.play_pause()?,
+            PlayerCommand::Play => player.play()?,
+            PlayerCommand::Pause => player.pause()?,
+            PlayerCommand::Stop => player.stop()?,
+            PlayerCommand::Next => player.next()?,
+            PlayerCommand::Previous => player.previous()?,
+            PlayerCommand::SetPosition { track_id, position } => {
+                player.set_position(TrackID::new(track_id.as_str()).map_err(|e| anyhow!(e))?, position)?
+            }
+            PlayerCommand::SetRate(rate) => player.set_playback_rate(*rate)?,
+            PlayerCommand::Raise => player.raise()?,
+            PlayerCommand::Quit => player.quit()?,
+            PlayerCommand::OpenUri(_) => unreachable!("sent above"),
+            PlayerCommand::SetVolume(volume) => player.set_volume(*volume)?,
+            PlayerCommand::SetRating(_) => bail!("MPRIS players cannot be rated"),
+        }
+        Ok(())
+    }
+}
+
+/// Several backends shown as one, e.g. MPRIS players next to the built-in
+/// player. Commands are routed to the backend that reported the player.
+pub struct MultiBackend {
+    backends: Vec<Box<dyn PlayerBackend>>,
+    /// Player id -> index into `backends`
+    owners: HashMap<String, usize>,
+}
+
+impl MultiBackend {
+    pub fn new(backends: Vec<Box<dyn PlayerBackend>>) -> Self {
+        Self {
+            backends,
+            owners: HashMap::new(),
+        }
+    }
+
+    fn owner(&self, id: &str) -> Result<&dyn PlayerBackend> {
+        self.owners
+            .get(id)
+            .map(|index| self.backends[*index].as_ref())
+            .ok_or_else(|| anyhow!("Player {} is no longer available", id))
+    }
+}
+
+impl PlayerBackend for MultiBackend {
+    fn find_all(&mut self) -> Result<Vec<PlayerEntry>> {
+        self.owners.clear();
+        let mut entries = Vec::new();
+        let mut errors = Vec::new();
+        for (index, backend) in self.backends.iter_mut().enumerate() {
+            match backend.find_all() {
+                Ok(found) => {
+                    for entry in found {
+                        self.owners.insert(entry.id.clone(), index);
+                        entries.push(entry);
+                    }
+                }
+                Err(e) => errors.push(e),
+            }
+        }
+
+        // One backend failing (e.g. no session bus) must not hide the players of the others
+        if entries.is_empty() && !errors.is_empty() {
+            return Err(errors.remove(0));
+        }
+        for e in errors {
+            debug!("Player discovery failed: {:#}", e);
+        }
+        Ok(entries)
+    }
+
+    fn playback_status(&self, id: &str) -> Result<PlaybackStatus> {
+        self.owner(id)?.playback_status(id)
+    }
+
+    fn metadata(&self, id: &str) -> Result<Metadata> {
+        self.owner(id)?.metadata(id)
+    }
+
+    fn position(&self, id: &str) -> Result<Duration> {
+        self.owner(id)?.position(id)
+    }
+
+    fn rate(&self, id: &str) -> Result<Option<PlaybackRate>> {
+        self.owner(id)?.rate(id)
+    }
+
+    fn volume(&self, id: &str) -> Result<Option<f64>> {
+        self.owner(id)?.volume(id)
+    }
+
+    fn capabilities(&self, id: &str) -> Result<Capabilities> {
+        self.owner(id)?.capabilities(id)
+    }
+
+    fn supported_uri_schemes(&self, id: &str) -> Result<Vec<String>> {
+        self.owner(id)?.supported_uri_schemes(id)
+    }
+
+    fn send(&mut self, id: &str, command: &PlayerCommand) -> Result<()> {
+        let index = *self
+            .owners
+            .get(id)
+            .ok_or_else(|| anyhow!("Player {} is no longer available", id))?;
+        self.backends[index].send(id, command)
+    }
+}
+
+/// In-memory backend for tests.
+///
+/// Clones share state, so a test can keep a handle to change players or
+/// inspect sent commands after moving the backend into a `MusicPlayer`.
+#[cfg(test)]
+pub mod fake {
+    use super::*;
+    use mpris::MetadataValue;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[derive(Debug, Clone)]
+    pub struct FakePlayer {
+        pub entry: PlayerEntry,
+        pub status: PlaybackStatus,
+        pub metadata: Metadata,
+        pub position: Duration,
+        pub rate: Option<PlaybackRate>,
+        pub capabilities: Capabilities,
+        pub uri_schemes: Vec<String>,
+        pub volume: Option<f64>,
+    }
+
+    impl FakePlayer {
+        pub fn new(id: &str, identity: &str) -> Self {
+            Self {
+                entry: PlayerEntry {
+                    id: id.to_string(),
+                    identity: identity.to_string(),
+                },
+                status: PlaybackStatus::Stopped,
+                metadata: Metadata::new(format!("/{}/track/0", identity)),
+                position: Duration::ZERO,
+                rate: None,
+                capabilities: Capabilities::default(),
+                uri_schemes: vec!["file".to_string()],
+                volume: None,
+            }
+        }
+
+        pub fn status(mut self, status: PlaybackStatus) -> Self {
+            self.status = status;
+            self
+        }
+
+        pub fn position(mut self, position: Duration) -> Self {
+            self.position = position;
+            self
+        }
+
+        pub fn capabilities(mut self, capabilities: Capabilities) -> Self {
+            self.capabilities = capabilities;
+            self
+        }
+
+        pub fn volume(mut self, volume: f64) -> Self {
+            self.volume = Some(volume);
+            self
+        }
+
+        pub fn uri_schemes(mut self, schemes: &[&str]) -> Self {
+            self.uri_schemes = schemes.iter().map(|s| s.to_string()).collect();
+            self
+        }
+
+        pub fn rate(mut self, current: f64, minimum: f64, maximum: f64) -> Self {
+            self.rate = Some(PlaybackRate { current, minimum, maximum });
+            self
+        }
+
+        pub fn with(mut self, key: &str, value: impl Into<MetadataValue>) -> Self {
+            let mut values: HashMap<String, MetadataValue> = self.metadata.into();
+            values.insert(key.to_string(), value.into());
+            self.metadata = values.into();
+            self
+        }
+    }
+
+    #[derive(Debug, Default)]
+    pub struct FakeState {
+        pub players: Vec<FakePlayer>,
+        pub sent: Vec<(String, PlayerCommand)>,
+        pub fail_discovery: bool,
+        pub bus_down: bool,
+        /// Players reject every command
+        pub fail_commands: bool,
+    }
+
+    #[derive(Debug, Clone, Default)]
+    pub struct FakeBackend {
+        pub state: Rc<RefCell<FakeState>>,
+    }
+
+    impl FakeBackend {
+        pub fn with_players(players: Vec<FakePlayer>) -> Self {
+            let backend = Self::default();
+            backend.state.borrow_mut().players = players;
+            backend
+        }
+
+        pub fn set_players(&self, players: Vec<FakePlayer>) {
+            self.state.borrow_mut().players = players;
+        }
+
+        pub fn sent(&self) -> Vec<(String, PlayerCommand)> {
+            self.state.borrow().sent.clone()
+        }
+
+        fn with_player<T>(&self, id: &str, f: impl FnOnce(&FakePlayer) -> T) -> Result<T> {
+            let state = self.state.borrow();
+            state
+                .players
+                .iter()
+                .find(|p| p.entry.id == id)
+                .map(f)
+                .ok_or_else(|| anyhow!("Player {} is no longer available", id))
+        }
+    }
+
+    impl PlayerBackend for FakeBackend {
+        fn find_all(&mut self) -> Result<Vec<PlayerEntry>> {
+            let state = self.state.borrow();
+            if state.bus_down {
+                return Err(SessionBusUnavailable { reason: "no bus".to_string() }.into());
+            }
+            if state.fail_discovery {
+                return Err(anyhow!("discovery failed"));
+            }
+            Ok(state.players.iter().map(|p| p.entry.clone()).collect())
+        }
+
+        fn playback_status(&self, id: &str) -> Result<PlaybackStatus> {
+            self.with_player(id, |p| p.status)
+        }
+
+        fn metadata(&self, id: &str) -> Result<Metadata> {
+            self.with_player(id, |p| p.metadata.clone())
+        }
+
+        fn position(&self, id: &str) -> Result<Duration> {
+            self.with_player(id, |p| p.position)
+        }
+
+        fn rate(&self, id: &str) -> Result<Option<PlaybackRate>> {
+            self.with_player(id, |p| p.rate)
+        }
+
+        fn volume(&self, id: &str) -> Result<Option<f64>> {
+            self.with_player(id, |p| p.volume)
+        }
+
+        fn capabilities(&self, id: &str) -> Result<Capabilities> {
+            self.with_player(id, |p| p.capabilities)
+        }
+
+        fn supported_uri_schemes(&self, id: &str) -> Result<Vec<String>> {
+            self.with_player(id, |p| p.uri_schemes.clone())
+        }
+
+        fn send(&mut self, id: &str, command: &PlayerCommand) -> Result<()> {
+            self.with_player(id, |_| ())?;
+            if self.state.borrow().fail_commands {
+                return Err(anyhow!("{:?} failed", command));
+            }
+            self.state.borrow_mut().sent.push((id.to_string(), command.clone()));
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fake::{FakeBackend, FakePlayer};
+    use super::*;
+
+    #[test]
+    fn multi_backend_routes_to_the_owning_backend() {
+        let first = FakeBackend::with_players(vec![FakePlayer::new("a", "A")]);
+        let second = FakeBackend::with_players(vec![FakePlayer::new("b", "B")]);
+        let mut backend = MultiBackend::new(vec![Box::new(first.clone()), Box::new(second.clone())]);
+
+        let ids: Vec<String> = backend.find_all().unwrap().into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec!["a", "b"]);
+        backend.send("b", &PlayerCommand::Next).unwrap();
+        assert!(first.sent().is_empty());
+        assert_eq!(second.sent(), vec![("b".to_string(), PlayerCommand::Next)]);
+    }
+
+    #[test]
+    fn multi_backend_keeps_players_of_working_backends() {
+        let first = FakeBackend::with_players(vec![FakePlayer::new("a", "A")]);
+        let second = FakeBackend::with_players(vec![FakePlayer::new("b", "B")]);
+        let mut backend = MultiBackend::new(vec![Box::new(first.clone()), Box::new(second.clone())]);
+
+        first.state.borrow_mut().bus_down = true;
+        assert_eq!(backend.find_all().unwrap().len(), 1);
+        assert!(backend.playback_status("a").is_err());
+
+        second.state.borrow_mut().bus_down = true;
+        assert!(backend.find_all().unwrap_err().is::<SessionBusUnavailable>());
+    }
+}
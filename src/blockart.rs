@@ -0,0 +1,518 @@
+use image::{imageops::FilterType, DynamicImage, GenericImageView};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    widgets::Widget,
+};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Characters the picture is drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArtStyle {
+    /// Two pixels per cell, one above the other
+    Halfblocks,
+    /// 2x2 pixels per cell in two colors
+    Quadrants,
+    /// 2x4 dots per cell in one color
+    Braille,
+    /// Characters from dark to bright
+    Ascii,
+}
+
+impl ArtStyle {
+    /// Pixels per cell, across and down
+    fn cell_pixels(self) -> (u32, u32) {
+        match self {
+            ArtStyle::Halfblocks => (1, 2),
+            ArtStyle::Quadrants => (2, 2),
+            ArtStyle::Braille => (2, 4),
+            ArtStyle::Ascii => (1, 2),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Dithering {
+    None,
+    /// 4x4 Bayer matrix, stable while the picture is redrawn
+    Ordered,
+    FloydSteinberg,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorDepth {
+    /// Truecolor if `COLORTERM` says so, 256 colors otherwise
+    Auto,
+    #[serde(rename = "16")]
+    Ansi16,
+    #[serde(rename = "256")]
+    Ansi256,
+    Truecolor,
+}
+
+impl ColorDepth {
+    fn resolve(self) -> ColorDepth {
+        match self {
+            ColorDepth::Auto => {
+                let colorterm = env::var("COLORTERM").unwrap_or_default();
+                if colorterm == "truecolor" || colorterm == "24bit" {
+                    ColorDepth::Truecolor
+                } else {
+                    ColorDepth::Ansi256
+                }
+            }
+            depth => depth,
+        }
+    }
+}
+
+/// How covers are drawn as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BlockArtConfig {
+    pub style: ArtStyle,
+    /// Applies to half blocks, quadrants and braille dots
+    pub dithering: Dithering,
+    pub colors: ColorDepth,
+}
+
+impl Default for BlockArtConfig {
+    fn default() -> Self {
+        Self {
+            style: ArtStyle::Halfblocks,
+            dithering: Dithering::FloydSteinberg,
+            colors: ColorDepth::Auto,
+        }
+    }
+}
+
+/// The 16 ANSI colors with xterm's default values
+const ANSI16: [(Color, [f32; 3]); 16] = [
+    (Color::Black, [0.0, 0.0, 0.0]),
+    (Color::Red, [205.0, 0.0, 0.0]),
+    (Color::Green, [0.0, 205.0, 0.0]),
+    (Color::Yellow, [205.0, 205.0, 0.0]),
+    (Color::Blue, [0.0, 0.0, 238.0]),
+    (Color::Magenta, [205.0, 0.0, 205.0]),
+    (Color::Cyan, [0.0, 205.0, 205.0]),
+    (Color::Gray, [229.0, 229.0, 229.0]),
+    (Color::DarkGray, [127.0, 127.0, 127.0]),
+    (Color::LightRed, [255.0, 0.0, 0.0]),
+    (Color::LightGreen, [0.0, 255.0, 0.0]),
+    (Color::LightYellow, [255.0, 255.0, 0.0]),
+    (Color::LightBlue, [92.0, 92.0, 255.0]),
+    (Color::LightMagenta, [255.0, 0.0, 255.0]),
+    (Color::LightCyan, [0.0, 255.0, 255.0]),
+    (Color::White, [255.0, 255.0, 255.0]),
+];
+
+/// Channel values of the 6x6x6 cube of the 256-color palette
+const CUBE_LEVELS: [f32; 6] = [0.0, 95.0, 135.0, 175.0, 215.0, 255.0];
+
+const BAYER: [[f32; 4]; 4] = [
+    [0.0, 8.0, 2.0, 10.0],
+    [12.0, 4.0, 14.0, 6.0],
+    [3.0, 11.0, 1.0, 9.0],
+    [15.0, 7.0, 13.0, 5.0],
+];
+
+/// Quadrant characters by mask: top left 1, top right 2, bottom left 4, bottom right 8
+const QUADRANTS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛', '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+/// Braille dot bits by position (x, y) in the cell
+const BRAILLE_DOTS: [[u32; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+
+const ASCII_RAMP: &[u8] = b" .:-=+*#%@";
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    (0..3).map(|i| (a[i] - b[i]).powi(2)).sum()
+}
+
+fn luma(rgb: [f32; 3]) -> f32 {
+    (0.299 * rgb[0] + 0.587 * rgb[1] + 0.114 * rgb[2]) / 255.0
+}
+
+fn average(colors: &[[f32; 3]]) -> [f32; 3] {
+    let mut sum = [0.0; 3];
+    for color in colors {
+        for i in 0..3 {
+            sum[i] += color[i];
+        }
+    }
+    sum.map(|c| c / colors.len().max(1) as f32)
+}
+
+/// The closest color `depth` can show, and its value
+fn nearest(rgb: [f32; 3], depth: ColorDepth) -> (Color, [f32; 3]) {
+    let rgb = rgb.map(|c| c.clamp(0.0, 255.0));
+    match depth {
+        ColorDepth::Auto | ColorDepth::Truecolor => {
+            let [r, g, b] = rgb.map(|c| c.round() as u8);
+            (Color::Rgb(r, g, b), rgb)
+        }
+        ColorDepth::Ansi16 => ANSI16
+            .iter()
+            .min_by(|a, b| distance(rgb, a.1).total_cmp(&distance(rgb, b.1)))
+            .copied()
+            .unwrap(),
+        ColorDepth::Ansi256 => {
+            let level = |c: f32| {
+                (0..6)
+                    .min_by(|a, b| (CUBE_LEVELS[*a] - c).abs().total_cmp(&(CUBE_LEVELS[*b] - c).abs()))
+                    .unwrap()
+            };
+            let (r, g, b) = (level(rgb[0]), level(rgb[1]), level(rgb[2]));
+            let cube = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+
+            // The gray ramp: 232 to 255 are 8, 18, ..., 238
+            let gray_index = ((rgb[0] + rgb[1] + rgb[2]) / 3.0 - 8.0) / 10.0;
+            let gray_index = gray_index.round().clamp(0.0, 23.0);
+            let gray_value = 8.0 + gray_index * 10.0;
+            let gray = [gray_value; 3];
+
+            if distance(rgb, gray) < distance(rgb, cube) {
+                (Color::Indexed(232 + gray_index as u8), gray)
+            } else {
+                (Color::Indexed(16 + 36 * r as u8 + 6 * g as u8 + b as u8), cube)
+            }
+        }
+    }
+}
+
+/// Bayer threshold for pixel (x, y), from 0.0 to 1.0
+fn bayer(x: u32, y: u32) -> f32 {
+    (BAYER[(y % 4) as usize][(x % 4) as usize] + 0.5) / 16.0
+}
+
+/// The picture resized to the pixels of the cells it is drawn in
+struct Pixels {
+    width: u32,
+    height: u32,
+    data: Vec<[f32; 3]>,
+}
+
+impl Pixels {
+    fn new(image: &DynamicImage, width: u32, height: u32) -> Self {
+        let small = image.resize_exact(width, height, FilterType::Triangle).to_rgb8();
+        let data = small.pixels().map(|p| p.0.map(f32::from)).collect();
+        Self { width, height, data }
+    }
+
+    fn get(&self, x: u32, y: u32) -> [f32; 3] {
+        self.data[(y * self.width + x) as usize]
+    }
+
+    fn add_error(&mut self, x: i64, y: i64, error: [f32; 3], weight: f32) {
+        if x < 0 || y < 0 || x >= self.width as i64 || y >= self.height as i64 {
+            return;
+        }
+        let pixel = &mut self.data[(y as u32 * self.width + x as u32) as usize];
+        for i in 0..3 {
+            pixel[i] += error[i] * weight;
+        }
+    }
+
+    /// Snap every pixel to the colors of `depth`, spreading the difference
+    /// to the neighbours as `dithering` says
+    fn quantize(&mut self, depth: ColorDepth, dithering: Dithering) {
+        // Roughly the distance between two palette values on one channel
+        let spread = match depth {
+            ColorDepth::Ansi16 => 128.0,
+            ColorDepth::Ansi256 => 48.0,
+            ColorDepth::Auto | ColorDepth::Truecolor => return,
+        };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let old = self.get(x, y);
+                let wanted = match dithering {
+                    Dithering::Ordered => old.map(|c| c + (bayer(x, y) - 0.5) * spread),
+                    Dithering::None | Dithering::FloydSteinberg => old,
+                };
+                let (_, new) = nearest(wanted, depth);
+                self.data[(y * self.width + x) as usize] = new;
+                if dithering == Dithering::FloydSteinberg {
+                    let error = [old[0] - new[0], old[1] - new[1], old[2] - new[2]];
+                    let (x, y) = (x as i64, y as i64);
+                    self.add_error(x + 1, y, error, 7.0 / 16.0);
+                    self.add_error(x - 1, y + 1, error, 3.0 / 16.0);
+                    self.add_error(x, y + 1, error, 5.0 / 16.0);
+                    self.add_error(x + 1, y + 1, error, 1.0 / 16.0);
+                }
+            }
+        }
+    }
+
+    /// Which pixels are bright enough to be a dot
+    fn dots(&self, dithering: Dithering) -> Vec<bool> {
+        let mut levels: Vec<f32> = self.data.iter().map(|p| luma(*p)).collect();
+        let mut dots = vec![false; levels.len()];
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let index = (y * self.width + x) as usize;
+                let threshold = if dithering == Dithering::Ordered { bayer(x, y) } else { 0.5 };
+                let on = levels[index] > threshold;
+                dots[index] = on;
+                if dithering == Dithering::FloydSteinberg {
+                    let error = levels[index] - if on { 1.0 } else { 0.0 };
+                    let mut spread = |dx: i64, dy: i64, weight: f32| {
+                        let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                        if nx >= 0 && nx < self.width as i64 && ny < self.height as i64 {
+                            levels[(ny as u32 * self.width + nx as u32) as usize] += error * weight;
+                        }
+                    };
+                    spread(1, 0, 7.0 / 16.0);
+                    spread(-1, 1, 3.0 / 16.0);
+                    spread(0, 1, 5.0 / 16.0);
+                    spread(1, 1, 1.0 / 16.0);
+                }
+            }
+        }
+        dots
+    }
+}
+
+/// A picture drawn with colored text characters, for terminals without
+/// graphics support and for screen sharing
+pub struct BlockArt<'a> {
+    image: &'a DynamicImage,
+    config: BlockArtConfig,
+}
+
+impl<'a> BlockArt<'a> {
+    pub fn new(image: &'a DynamicImage, config: BlockArtConfig) -> Self {
+        Self { image, config }
+    }
+
+    /// The cells of `area` the picture takes, centered and keeping its aspect ratio
+    fn placement(&self, area: Rect) -> Option<Rect> {
+        let (width, height) = self.image.dimensions();
+        if area.is_empty() || width == 0 || height == 0 {
+            return None;
+        }
+        // Cells are about twice as high as wide
+        let scale = f64::min(area.width as f64 / width as f64, area.height as f64 * 2.0 / height as f64);
+        let columns = ((width as f64 * scale).round() as u16).clamp(1, area.width);
+        let rows = ((height as f64 * scale / 2.0).round() as u16).clamp(1, area.height);
+        let x0 = area.x + (area.width - columns) / 2;
+        let y0 = area.y + (area.height - rows) / 2;
+        Some(Rect::new(x0, y0, columns, rows))
+    }
+}
+
+impl Widget for BlockArt<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let Some(Rect { x: x0, y: y0, width: columns, height: rows }) = self.placement(area) else {
+            return;
+        };
+
+        let style = self.config.style;
+        let depth = self.config.colors.resolve();
+        let (cell_width, cell_height) = style.cell_pixels();
+        let mut pixels = Pixels::new(self.image, columns as u32 * cell_width, rows as u32 * cell_height);
+        let dots = (style == ArtStyle::Braille).then(|| pixels.dots(self.config.dithering));
+        if matches!(style, ArtStyle::Halfblocks | ArtStyle::Quadrants) {
+            pixels.quantize(depth, self.config.dithering);
+        }
+
+        for row in 0..rows as u32 {
+            for column in 0..columns as u32 {
+                let cell: Vec<[f32; 3]> = (0..cell_height)
+                    .flat_map(|dy| (0..cell_width).map(move |dx| (dx, dy)))
+                    .map(|(dx, dy)| pixels.get(column * cell_width + dx, row * cell_height + dy))
+                    .collect();
+                let (symbol, fg, bg) = match style {
+                    ArtStyle::Halfblocks => ('▀', nearest(cell[0], depth).0, Some(nearest(cell[1], depth).0)),
+                    ArtStyle::Quadrants => {
+                        // Split the four pixels into a bright and a dark group
+                        let levels: Vec<f32> = cell.iter().map(|p| luma(*p)).collect();
+                        let mean = levels.iter().sum::<f32>() / 4.0;
+                        let mask = (0..4).filter(|i| levels[*i] > mean).fold(0, |mask, i| mask | 1 << i);
+                        let bright: Vec<[f32; 3]> = (0..4).filter(|i| mask & 1 << i != 0).map(|i| cell[i]).collect();
+                        let dark: Vec<[f32; 3]> = (0..4).filter(|i| mask & 1 << i == 0).map(|i| cell[i]).collect();
+                        let bg = (!dark.is_empty()).then(|| nearest(average(&dark), depth).0);
+                        (QUADRANTS[mask], nearest(average(&bright), depth).0, bg)
+                    }
+                    ArtStyle::Braille => {
+                        let dots = dots.as_ref().unwrap();
+                        let mut bits = 0;
+                        let mut lit = Vec::new();
+                        for dy in 0..cell_height {
+                            for dx in 0..cell_width {
+                                let (x, y) = (column * cell_width + dx, row * cell_height + dy);
+                                if dots[(y * pixels.width + x) as usize] {
+                                    bits |= BRAILLE_DOTS[dx as usize][dy as usize];
+                                    lit.push(pixels.get(x, y));
+                                }
+                            }
+                        }
+                        let symbol = char::from_u32(0x2800 + bits).unwrap_or(' ');
+                        (symbol, nearest(average(&lit), depth).0, None)
+                    }
+                    ArtStyle::Ascii => {
+                        let color = average(&cell);
+                        let index = (luma(color) * (ASCII_RAMP.len() - 1) as f32).round() as usize;
+                        (ASCII_RAMP[index.min(ASCII_RAMP.len() - 1)] as char, nearest(color, depth).0, None)
+                    }
+                };
+
+                let mut cell_style = Style::default().fg(fg);
+                if let Some(bg) = bg {
+                    cell_style = cell_style.bg(bg);
+                }
+                buf[(x0 + column as u16, y0 + row as u16)].set_char(symbol).set_style(cell_style);
+            }
+        }
+    }
+}
+
+/// A picture with its last drawing. Resampling and dithering a large cover
+/// takes long, so they only run again when the area changes size.
+pub struct TextArt {
+    image: DynamicImage,
+    config: BlockArtConfig,
+    /// Cells drawn for an area of this size, placed at 0, 0
+    drawn: Option<Buffer>,
+}
+
+impl TextArt {
+    pub fn new(image: DynamicImage, config: BlockArtConfig) -> Self {
+        Self { image, config, drawn: None }
+    }
+}
+
+impl Widget for &mut TextArt {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let size = Rect::new(0, 0, area.width, area.height);
+        if self.drawn.as_ref().map(|drawn| drawn.area) != Some(size) {
+            let mut drawn = Buffer::empty(size);
+            BlockArt::new(&self.image, self.config).render(size, &mut drawn);
+            self.drawn = Some(drawn);
+        }
+        let drawn = self.drawn.as_ref().unwrap();
+        // Only the cells of the picture, like `BlockArt` leaves the rest alone
+        let Some(placed) = BlockArt::new(&self.image, self.config).placement(size) else {
+            return;
+        };
+        for y in placed.top()..placed.bottom() {
+            for x in placed.left()..placed.right() {
+                buf[(area.x + x, area.y + y)] = drawn[(x, y)].clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn draw(image: &RgbImage, config: BlockArtConfig, width: u16, height: u16) -> Buffer {
+        let image = DynamicImage::ImageRgb8(image.clone());
+        let area = Rect::new(0, 0, width, height);
+        let mut buf = Buffer::empty(area);
+        BlockArt::new(&image, config).render(area, &mut buf);
+        buf
+    }
+
+    fn config(style: ArtStyle, dithering: Dithering, colors: ColorDepth) -> BlockArtConfig {
+        BlockArtConfig {
+            style,
+            dithering,
+            colors,
+        }
+    }
+
+    #[test]
+    fn half_blocks_show_two_pixels_per_cell() {
+        // Top half red, bottom half blue
+        let image = RgbImage::from_fn(4, 4, |_, y| if y < 2 { Rgb([255, 0, 0]) } else { Rgb([0, 0, 255]) });
+        let buf = draw(&image, config(ArtStyle::Halfblocks, Dithering::None, ColorDepth::Truecolor), 4, 2);
+
+        assert_eq!(buf[(0, 0)].symbol(), "▀");
+        assert_eq!(buf[(0, 0)].fg, Color::Rgb(255, 0, 0));
+        assert_eq!(buf[(0, 0)].bg, Color::Rgb(255, 0, 0));
+        assert_eq!(buf[(0, 1)].bg, Color::Rgb(0, 0, 255));
+    }
+
+    #[test]
+    fn quadrants_follow_the_bright_pixels() {
+        // White left column on black
+        let image = RgbImage::from_fn(2, 2, |x, _| if x == 0 { Rgb([255, 255, 255]) } else { Rgb([0, 0, 0]) });
+        let buf = draw(&image, config(ArtStyle::Quadrants, Dithering::None, ColorDepth::Ansi16), 1, 1);
+
+        assert_eq!(buf[(0, 0)].symbol(), "▌");
+        assert_eq!(buf[(0, 0)].fg, Color::White);
+        assert_eq!(buf[(0, 0)].bg, Color::Black);
+    }
+
+    #[test]
+    fn braille_dots_are_bright_pixels() {
+        let white = RgbImage::from_pixel(4, 4, Rgb([255, 255, 255]));
+        let buf = draw(&white, config(ArtStyle::Braille, Dithering::FloydSteinberg, ColorDepth::Ansi256), 2, 1);
+        assert_eq!(buf[(0, 0)].symbol(), "⣿");
+        assert_eq!(buf[(0, 0)].fg, Color::Indexed(231));
+
+        let black = RgbImage::from_pixel(4, 4, Rgb([0, 0, 0]));
+        let buf = draw(&black, config(ArtStyle::Braille, Dithering::None, ColorDepth::Ansi256), 2, 1);
+        assert_eq!(buf[(0, 0)].symbol(), "⠀");
+    }
+
+    #[test]
+    fn dithering_mixes_palette_colors_for_in_between_shades() {
+        // Mid gray has no exact match among the 16 colors
+        let gray = RgbImage::from_pixel(8, 8, Rgb([180, 180, 180]));
+        for dithering in [Dithering::Ordered, Dithering::FloydSteinberg] {
+            let buf = draw(&gray, config(ArtStyle::Halfblocks, dithering, ColorDepth::Ansi16), 8, 4);
+            let mut colors: Vec<Color> = buf.content().iter().flat_map(|cell| [cell.fg, cell.bg]).collect();
+            colors.sort_by_key(|c| format!("{:?}", c));
+            colors.dedup();
+            assert!(colors.len() > 1, "{:?} gave only {:?}", dithering, colors);
+        }
+
+        let buf = draw(&gray, config(ArtStyle::Halfblocks, Dithering::None, ColorDepth::Ansi16), 8, 4);
+        assert!(buf.content().iter().all(|cell| cell.fg == Color::DarkGray || cell.fg == Color::Gray));
+    }
+
+    #[test]
+    fn colors_match_the_palette() {
+        assert_eq!(nearest([250.0, 10.0, 10.0], ColorDepth::Ansi16).0, Color::LightRed);
+        assert_eq!(nearest([0.0, 0.0, 0.0], ColorDepth::Ansi256).0, Color::Indexed(16));
+        assert_eq!(nearest([128.0, 128.0, 128.0], ColorDepth::Ansi256).0, Color::Indexed(244));
+        assert_eq!(nearest([255.0, 135.0, 0.0], ColorDepth::Ansi256).0, Color::Indexed(208));
+        assert_eq!(nearest([1.0, 2.0, 3.0], ColorDepth::Truecolor).0, Color::Rgb(1, 2, 3));
+    }
+
+    #[test]
+    fn text_art_draws_like_block_art_at_any_position() {
+        let image = RgbImage::from_fn(16, 8, |x, y| Rgb([(x * 16) as u8, (y * 32) as u8, 128]));
+        let config = config(ArtStyle::Quadrants, Dithering::FloydSteinberg, ColorDepth::Ansi256);
+        let mut art = TextArt::new(DynamicImage::ImageRgb8(image.clone()), config);
+
+        for (width, height) in [(8, 4), (8, 4), (6, 6)] {
+            let expected = draw(&image, config, width, height);
+            let mut buf = Buffer::empty(Rect::new(0, 0, width + 3, height + 2));
+            art.render(Rect::new(3, 2, width, height), &mut buf);
+            for (x, y) in (0..height).flat_map(|y| (0..width).map(move |x| (x, y))) {
+                assert_eq!(buf[(x + 3, y + 2)], expected[(x, y)], "{}x{} at {}, {}", width, height, x, y);
+            }
+            assert_eq!(buf[(0, 0)], ratatui::buffer::Cell::default());
+        }
+    }
+
+    #[test]
+    fn keeps_the_aspect_ratio() {
+        // A wide picture in a square-ish area leaves rows empty above and below
+        let image = RgbImage::from_pixel(40, 10, Rgb([255, 255, 255]));
+        let buf = draw(&image, config(ArtStyle::Ascii, Dithering::None, ColorDepth::Truecolor), 10, 10);
+        assert_eq!(buf[(0, 0)].symbol(), " ");
+        assert_eq!(buf[(0, 0)].fg, Color::Reset);
+        assert_eq!(buf[(0, 4)].symbol(), "@");
+    }
+}
@@ -0,0 +1,170 @@
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use log::LevelFilter;
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::cover::CoverConfig;
+use crate::i18n::Language;
+use crate::marquee::MarqueeConfig;
+use crate::mpd::MpdConfig;
+use crate::policy::PolicyConfig;
+use crate::resume::ResumeConfig;
+
+const APP_NAME: &str = "music-tray";
+
+/// Command line arguments
+#[derive(Debug, Parser)]
+#[command(name = APP_NAME, version, about = "Control MPRIS music players from the terminal")]
+pub struct Cli {
+    /// Config file to use instead of $XDG_CONFIG_HOME/music-tray/config.toml
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Log level (off, error, warn, info, debug, trace); overrides RUST_LOG and the config
+    #[arg(long, value_name = "LEVEL")]
+    pub log_level: Option<LevelFilter>,
+
+    /// Play a directory, M3U playlist or file with the built-in player
+    #[arg(long, value_name = "PATH")]
+    pub play: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// Commands that run without the TUI
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Open a URL or local file in a player
+    Open {
+        /// URL or path to open
+        uri: String,
+        /// Player to use, matched against part of its name; the first player otherwise
+        #[arg(long, short)]
+        player: Option<String>,
+    },
+}
+
+/// Settings read from the config file. Every field is optional in the file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// UI language; from the locale if unset
+    pub language: Option<Language>,
+    pub log: LogConfig,
+    pub policy: PolicyConfig,
+    pub resume: ResumeConfig,
+    pub local: LocalConfig,
+    pub mpd: MpdConfig,
+    pub cover: CoverConfig,
+    pub marquee: MarqueeConfig,
+}
+
+/// The built-in player (needs the `local-player` feature)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LocalConfig {
+    /// Directory, M3U playlist or file to play; no built-in player if unset
+    pub path: Option<PathBuf>,
+    /// Where the audio goes: "null" or "file:<path>" for raw 16-bit PCM
+    pub sink: String,
+}
+
+impl Default for LocalConfig {
+    fn default() -> Self {
+        Self {
+            path: None,
+            sink: "null".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LogConfig {
+    pub level: LevelFilter,
+    /// Rotate the log file once it grows past this many KiB
+    pub max_size_kb: u64,
+    /// Number of rotated log files to keep next to the current one
+    pub keep: usize,
+    /// Show the log pane on startup
+    pub show_pane: bool,
+}
+
+impl Default for LogConfig {
+    fn default() -> Self {
+        Self {
+            level: LevelFilter::Info,
+            max_size_kb: 1024,
+            keep: 3,
+            show_pane: false,
+        }
+    }
+}
+
+impl Config {
+    /// Load the config from `path`, or from the default location if `None`.
+    /// A missing default config file is not an error.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => (config_dir().join("config.toml"), false),
+        };
+
+        match fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents)
+                .with_context(|| format!("Invalid config file {}", path.display())),
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("Failed to read config file {}", path.display())),
+        }
+    }
+}
+
+fn xdg_dir(var: &str, fallback: &str) -> PathBuf {
+    let base = env::var_os(var)
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .unwrap_or_else(|| {
+            env::var_os("HOME")
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join(fallback)
+        });
+    base.join(APP_NAME)
+}
+
+/// `$XDG_CONFIG_HOME/music-tray`
+pub fn config_dir() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config")
+}
+
+/// `$XDG_STATE_HOME/music-tray`
+pub fn state_dir() -> PathBuf {
+    xdg_dir("XDG_STATE_HOME", ".local/state")
+}
+
+pub fn cache_dir() -> PathBuf {
+    xdg_dir("XDG_CACHE_HOME", ".cache")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_config_uses_defaults() {
+        let config: Config = toml::from_str("[log]\nlevel = \"debug\"\n").unwrap();
+        assert_eq!(config.log.level, LevelFilter::Debug);
+        assert_eq!(config.log.keep, LogConfig::default().keep);
+    }
+
+    #[test]
+    fn explicit_config_must_exist() {
+        let dir = tempfile::tempdir().unwrap();
+        assert!(Config::load(Some(&dir.path().join("missing.toml"))).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+use image::DynamicImage;
+use log::{error, info, warn};
+use ratatui::{layout::Rect, Frame};
+use ratatui_image::{
+    picker::{Capability, Picker, ProtocolType},
+    protocol::StatefulProtocol,
+    Resize, StatefulImage,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::blockart::{ArtStyle, BlockArtConfig, TextArt};
+use crate::lookup::LookupConfig;
+use crate::mux::{self, Multiplexer};
+use crate::i18n::Message;
+
+/// Cell size assumed when the terminal does not report one; only the
+/// aspect ratio matters for half blocks
+const FALLBACK_FONT_SIZE: (u16, u16) = (10, 20);
+
+/// How covers are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CoverProtocol {
+    /// Whatever the terminal supports, tried in the order of `fallback`
+    Auto,
+    Kitty,
+    Sixel,
+    Iterm2,
+    /// Colored half blocks, which work in any terminal with colors
+    Halfblocks,
+    /// Colored text characters, drawn as `art` says
+    Ascii,
+}
+
+impl CoverProtocol {
+    pub fn name(self) -> &'static str {
+        match self {
+            CoverProtocol::Auto => "auto",
+            CoverProtocol::Kitty => "kitty",
+            CoverProtocol::Sixel => "sixel",
+            CoverProtocol::Iterm2 => "iTerm2",
+            CoverProtocol::Halfblocks => "half blocks",
+            CoverProtocol::Ascii => "text art",
+        }
+    }
+
+    /// Message key of the name shown in the UI
+    fn key(self) -> &'static str {
+        match self {
+            CoverProtocol::Auto => "protocol.auto",
+            CoverProtocol::Kitty => "protocol.kitty",
+            CoverProtocol::Sixel => "protocol.sixel",
+            CoverProtocol::Iterm2 => "protocol.iterm2",
+            CoverProtocol::Halfblocks => "protocol.halfblocks",
+            CoverProtocol::Ascii => "protocol.ascii",
+        }
+    }
+
+    /// Escape sequences a multiplexer has to pass on to the terminal
+    fn needs_passthrough(self) -> bool {
+        matches!(self, CoverProtocol::Kitty | CoverProtocol::Sixel | CoverProtocol::Iterm2)
+    }
+
+    fn graphics_type(self) -> Option<ProtocolType> {
+        match self {
+            CoverProtocol::Kitty => Some(ProtocolType::Kitty),
+            CoverProtocol::Sixel => Some(ProtocolType::Sixel),
+            CoverProtocol::Iterm2 => Some(ProtocolType::Iterm2),
+            CoverProtocol::Halfblocks => Some(ProtocolType::Halfblocks),
+            CoverProtocol::Auto | CoverProtocol::Ascii => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CoverConfig {
+    pub protocol: CoverProtocol,
+    /// Order in which `auto` tries the protocols
+    pub fallback: Vec<CoverProtocol>,
+    /// Text art settings, for `ascii`
+    pub art: BlockArtConfig,
+    /// Send graphics through tmux and screen; text art inside them otherwise
+    pub passthrough: bool,
+    /// Memory for decoded covers, so switching back to a track is instant
+    pub cache_mb: usize,
+    /// Where to find covers for tracks that come without one
+    pub lookup: LookupConfig,
+}
+
+impl Default for CoverConfig {
+    fn default() -> Self {
+        Self {
+            protocol: CoverProtocol::Auto,
+            fallback: vec![
+                CoverProtocol::Kitty,
+                CoverProtocol::Sixel,
+                CoverProtocol::Iterm2,
+                CoverProtocol::Halfblocks,
+                CoverProtocol::Ascii,
+            ],
+            art: BlockArtConfig::default(),
+            passthrough: true,
+            cache_mb: 64,
+            lookup: LookupConfig::default(),
+        }
+    }
+}
+
+/// The protocol to use: the configured one, or else the first one of the
+/// fallback chain the terminal supports. Text art always works.
+pub fn choose(config: &CoverConfig, supported: &[CoverProtocol]) -> CoverProtocol {
+    if config.protocol != CoverProtocol::Auto {
+        return config.protocol;
+    }
+    config
+        .fallback
+        .iter()
+        .copied()
+        .find(|protocol| supported.contains(protocol))
+        .unwrap_or(CoverProtocol::Ascii)
+}
+
+/// Protocols a terminal supports, from what it answered to the query
+fn supported_protocols(capabilities: &[Capability], detected: ProtocolType) -> Vec<CoverProtocol> {
+    let mut supported = Vec::new();
+    if detected == ProtocolType::Kitty || capabilities.contains(&Capability::Kitty) {
+        supported.push(CoverProtocol::Kitty);
+    }
+    if detected == ProtocolType::Sixel || capabilities.contains(&Capability::Sixel) {
+        supported.push(CoverProtocol::Sixel);
+    }
+    // Only guessed from the environment, terminals do not announce it
+    if detected == ProtocolType::Iterm2 {
+        supported.push(CoverProtocol::Iterm2);
+    }
+    supported.extend([CoverProtocol::Halfblocks, CoverProtocol::Ascii]);
+    supported
+}
+
+/// A cover ready to be drawn with the chosen protocol
+pub enum CoverImage {
+    Graphics(StatefulProtocol),
+    Text(TextArt),
+}
+
+impl CoverImage {
+    pub fn render(&mut self, f: &mut Frame, area: Rect) {
+        match self {
+            CoverImage::Graphics(protocol) => f.render_stateful_widget(StatefulImage::new(), area, protocol),
+            CoverImage::Text(art) => f.render_widget(art, area),
+        }
+    }
+
+    /// Like `render`, but scaled up to fill `area` when the image is smaller
+    pub fn render_zoomed(&mut self, f: &mut Frame, area: Rect) {
+        match self {
+            CoverImage::Graphics(protocol) => {
+                f.render_stateful_widget(StatefulImage::default().resize(Resize::Scale(None)), area, protocol)
+            }
+            CoverImage::Text(..) => self.render(f, area),
+        }
+    }
+}
+
+/// Picks the cover protocol on first use and prepares images for it
+pub struct CoverRenderer {
+    config: CoverConfig,
+    picker: Option<Picker>,
+    /// How covers are drawn without a picker
+    art: BlockArtConfig,
+    protocol: Option<CoverProtocol>,
+    /// Multiplexer we run in and whether it passes graphics on
+    multiplexer: Option<(Multiplexer, bool)>,
+    /// Message key for why the protocol was chosen, for the status line
+    reason: &'static str,
+    /// Whether a graphics protocol was given up for lack of passthrough
+    blocked: bool,
+}
+
+impl CoverRenderer {
+    pub fn new(config: CoverConfig) -> Self {
+        Self {
+            art: config.art,
+            config,
+            picker: None,
+            protocol: None,
+            multiplexer: None,
+            reason: "cover.reason.not_queried",
+            blocked: false,
+        }
+    }
+
+    /// The protocol in use, `None` until the terminal has been queried
+    pub fn protocol(&self) -> Option<CoverProtocol> {
+        self.protocol
+    }
+
+    /// Which protocol is in use and why
+    pub fn status(&self) -> Message {
+        let Some(protocol) = self.protocol else {
+            return Message::new("cover.undetected");
+        };
+        let message = match self.multiplexer {
+            Some((multiplexer, _)) => {
+                Message::new(if self.blocked { "cover.protocol_blocked" } else { "cover.protocol_in" })
+                    .arg("multiplexer", multiplexer.name())
+            }
+            None => Message::new("cover.protocol"),
+        };
+        message.arg_key("protocol", protocol.key()).arg_key("reason", self.reason)
+    }
+
+    /// The multiplexer to wrap graphics for, when they go through one
+    pub fn passthrough(&self) -> Option<Multiplexer> {
+        match (self.multiplexer, self.protocol) {
+            (Some((multiplexer, true)), Some(protocol)) if protocol.needs_passthrough() => Some(multiplexer),
+            _ => None,
+        }
+    }
+
+    /// Size of a terminal cell in pixels, querying the terminal first if needed
+    pub fn cell_size(&mut self) -> (u16, u16) {
+        if self.protocol.is_none() {
+            self.detect();
+        }
+        self.picker.as_ref().map_or(FALLBACK_FONT_SIZE, Picker::font_size)
+    }
+
+    /// Whether we run in tmux or screen
+    pub fn in_multiplexer(&self) -> bool {
+        self.multiplexer.is_some()
+    }
+
+    /// Query the terminal again on next use, e.g. after moving to another terminal
+    pub fn retry(&mut self) {
+        info!("Detecting the cover protocol again");
+        self.picker = None;
+        self.protocol = None;
+    }
+
+    /// Prepare `image` for drawing, querying the terminal first if needed
+    pub fn prepare(&mut self, image: DynamicImage) -> CoverImage {
+        if self.protocol.is_none() {
+            self.detect();
+        }
+        match &self.picker {
+            Some(picker) => CoverImage::Graphics(picker.new_resize_protocol(image)),
+            None => CoverImage::Text(TextArt::new(image, self.art)),
+        }
+    }
+
+    fn detect(&mut self) {
+        // Before the query: ratatui-image turns tmux's allow-passthrough on while querying
+        self.multiplexer = Multiplexer::detect().map(|multiplexer| {
+            let allowed = self.config.passthrough && multiplexer.allows_passthrough();
+            mux::log_passthrough(multiplexer, allowed);
+            (multiplexer, allowed)
+        });
+
+        let query = match self.multiplexer {
+            // Only the multiplexer would answer, the terminal never sees the query
+            Some((_, false)) => None,
+            Some((Multiplexer::Tmux, true)) => Some(mux::query_through_tmux()),
+            _ => Some(
+                Picker::from_query_stdio()
+                    .map(|picker| {
+                        let capabilities = picker.capabilities().clone();
+                        (picker, capabilities)
+                    })
+                    .map_err(anyhow::Error::from),
+            ),
+        };
+        let text_only = vec![CoverProtocol::Halfblocks, CoverProtocol::Ascii];
+        let (queried, mut supported, reason) = match query {
+            Some(Ok((picker, capabilities))) => {
+                let supported = supported_protocols(&capabilities, picker.protocol_type());
+                (Some(picker), supported, "cover.reason.detected")
+            }
+            Some(Err(e)) => {
+                error!("Terminal did not report its graphics support: {}", e);
+                (None, text_only, "cover.reason.query_failed")
+            }
+            None => (None, text_only, "cover.reason.not_queried"),
+        };
+        self.reason = if self.config.protocol == CoverProtocol::Auto { reason } else { "cover.reason.configured" };
+
+        let blocked = match self.multiplexer {
+            Some((multiplexer, allowed)) => {
+                if !allowed {
+                    supported.retain(|protocol| !protocol.needs_passthrough());
+                } else if multiplexer == Multiplexer::Screen {
+                    // screen cannot show kitty's placeholder characters
+                    supported.retain(|protocol| *protocol != CoverProtocol::Kitty);
+                }
+                !allowed
+            }
+            None => false,
+        };
+
+        let mut protocol = choose(&self.config, &supported);
+        self.blocked = blocked && protocol.needs_passthrough();
+        if self.blocked {
+            let text_only = CoverConfig {
+                protocol: CoverProtocol::Auto,
+                ..self.config.clone()
+            };
+            protocol = choose(&text_only, &supported);
+        } else if !supported.contains(&protocol) {
+            warn!("Using {} for covers although the terminal does not seem to support it", protocol.name());
+        }
+
+        self.art = self.config.art;
+        self.picker = match protocol.graphics_type() {
+            // Making a picker would turn tmux's passthrough on again; our own half blocks look the same
+            Some(ProtocolType::Halfblocks) if blocked => {
+                self.art.style = ArtStyle::Halfblocks;
+                None
+            }
+            Some(protocol_type) => {
+                let mut picker = queried.unwrap_or_else(|| Picker::from_fontsize(FALLBACK_FONT_SIZE));
+                picker.set_protocol_type(protocol_type);
+                Some(picker)
+            }
+            None => None,
+        };
+        self.protocol = Some(protocol);
+        info!("Drawing covers with {}", self.status());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn auto_takes_the_first_supported_protocol() {
+        let config = CoverConfig::default();
+        assert_eq!(choose(&config, &[CoverProtocol::Sixel, CoverProtocol::Kitty]), CoverProtocol::Kitty);
+        assert_eq!(choose(&config, &[CoverProtocol::Halfblocks]), CoverProtocol::Halfblocks);
+
+        let config = CoverConfig {
+            fallback: vec![CoverProtocol::Sixel, CoverProtocol::Ascii],
+            ..CoverConfig::default()
+        };
+        assert_eq!(choose(&config, &[CoverProtocol::Kitty, CoverProtocol::Halfblocks]), CoverProtocol::Ascii);
+        assert_eq!(choose(&CoverConfig { fallback: Vec::new(), ..config }, &[]), CoverProtocol::Ascii);
+    }
+
+    #[test]
+    fn configured_protocol_is_used_as_is() {
+        let config = CoverConfig {
+            protocol: CoverProtocol::Sixel,
+            ..CoverConfig::default()
+        };
+        assert_eq!(choose(&config, &[CoverProtocol::Kitty]), CoverProtocol::Sixel);
+        let config: CoverConfig = toml::from_str("protocol = \"iterm2\"").unwrap();
+        assert_eq!(config.protocol, CoverProtocol::Iterm2);
+    }
+
+    #[test]
+    fn query_answers_become_supported_protocols() {
+        assert_eq!(
+            supported_protocols(&[Capability::Sixel], ProtocolType::Sixel),
+            vec![CoverProtocol::Sixel, CoverProtocol::Halfblocks, CoverProtocol::Ascii]
+        );
+        assert_eq!(
+            supported_protocols(&[Capability::Kitty, Capability::Sixel], ProtocolType::Kitty),
+            vec![CoverProtocol::Kitty, CoverProtocol::Sixel, CoverProtocol::Halfblocks, CoverProtocol::Ascii]
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use image::{imageops::FilterType, DynamicImage};
+use log::debug;
+use std::collections::VecDeque;
+
+/// Which cover, decoded for which size in pixels
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CacheKey {
+    url: String,
+    size: (u32, u32),
+}
+
+/// Decoded covers, shrunk to the size they are drawn at and kept until they
+/// no longer fit in the memory budget, least recently used first out
+pub struct CoverCache {
+    /// Bytes of pixels we may keep
+    budget: usize,
+    used: usize,
+    /// Least recently used first
+    entries: VecDeque<(CacheKey, DynamicImage)>,
+}
+
+impl CoverCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            used: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    /// The cover at `url` fitted into `size` pixels (a zero size keeps it as
+    /// it is), decoded with `load` unless it is cached already
+    pub fn get(
+        &mut self,
+        url: &str,
+        size: (u32, u32),
+        load: impl FnOnce() -> Option<DynamicImage>,
+    ) -> Option<DynamicImage> {
+        let key = CacheKey {
+            url: url.to_string(),
+            size,
+        };
+        if let Some(index) = self.entries.iter().position(|(k, _)| *k == key) {
+            debug!("Cover cache hit for {} at {:?}", url, size);
+            let entry = self.entries.remove(index)?;
+            let image = entry.1.clone();
+            self.entries.push_back(entry);
+            return Some(image);
+        }
+
+        let image = fit(load()?, size);
+        self.insert(key, image.clone());
+        Some(image)
+    }
+
+    fn insert(&mut self, key: CacheKey, image: DynamicImage) {
+        let cost = image.as_bytes().len();
+        if cost > self.budget {
+            debug!("Cover {} is too large to cache ({} bytes)", key.url, cost);
+            return;
+        }
+        while self.used + cost > self.budget {
+            let Some((_, evicted)) = self.entries.pop_front() else {
+                break;
+            };
+            self.used -= evicted.as_bytes().len();
+        }
+        self.used += cost;
+        self.entries.push_back((key, image));
+    }
+}
+
+/// Shrink `image` to fit in `size`, keeping its aspect ratio; smaller images
+/// are left alone since the protocols scale them up anyway
+fn fit(image: DynamicImage, (width, height): (u32, u32)) -> DynamicImage {
+    if width == 0 || height == 0 || (image.width() <= width && image.height() <= height) {
+        return image;
+    }
+    image.resize(width, height, FilterType::Triangle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::RgbImage;
+
+    fn image(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgb8(RgbImage::new(width, height))
+    }
+
+    #[test]
+    fn cached_covers_are_not_loaded_again() {
+        let mut cache = CoverCache::new(1 << 20);
+        let mut loads = 0;
+        for _ in 0..3 {
+            let cover = cache.get("file:///a.jpg", (100, 100), || {
+                loads += 1;
+                Some(image(50, 50))
+            });
+            assert!(cover.is_some());
+        }
+        assert_eq!(loads, 1);
+
+        // Another size is another entry
+        cache.get("file:///a.jpg", (20, 20), || {
+            loads += 1;
+            Some(image(50, 50))
+        });
+        assert_eq!(loads, 2);
+    }
+
+    #[test]
+    fn covers_are_shrunk_to_the_target_size() {
+        let mut cache = CoverCache::new(1 << 20);
+        let cover = cache.get("file:///a.jpg", (100, 50), || Some(image(400, 400))).unwrap();
+        assert_eq!((cover.width(), cover.height()), (50, 50));
+        let cover = cache.get("file:///b.jpg", (100, 50), || Some(image(30, 20))).unwrap();
+        assert_eq!((cover.width(), cover.height()), (30, 20));
+        let cover = cache.get("file:///c.jpg", (0, 0), || Some(image(400, 400))).unwrap();
+        assert_eq!((cover.width(), cover.height()), (400, 400));
+    }
+
+    #[test]
+    fn least_recently_used_covers_leave_first() {
+        // Room for two 10x10 RGB images
+        let mut cache = CoverCache::new(2 * 300);
+        cache.get("a", (0, 0), || Some(image(10, 10)));
+        cache.get("b", (0, 0), || Some(image(10, 10)));
+        cache.get("a", (0, 0), || panic!("a is cached"));
+        cache.get("c", (0, 0), || Some(image(10, 10)));
+
+        assert!(cache.get("a", (0, 0), || panic!("a was used last")).is_some());
+        let mut reloaded = false;
+        cache.get("b", (0, 0), || {
+            reloaded = true;
+            Some(image(10, 10))
+        });
+        assert!(reloaded);
+        assert!(cache.used <= cache.budget);
+    }
+
+    #[test]
+    fn failures_and_oversized_covers_are_not_cached() {
+        let mut cache = CoverCache::new(100);
+        assert!(cache.get("missing", (0, 0), || None).is_none());
+        assert!(cache.get("big", (0, 0), || Some(image(10, 10))).is_some());
+        assert!(cache.entries.is_empty());
+        assert_eq!(cache.used, 0);
+    }
+}
@@ -0,0 +1,133 @@
+use anyhow::Result;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::json_store;
+use crate::music::TrackInfo;
+
+/// A liked track, for players that cannot store ratings themselves
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Favorite {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    /// Player the track was liked on
+    pub player: String,
+    /// Unix time it was liked
+    pub liked_at: u64,
+}
+
+impl Favorite {
+    fn matches(&self, artist: &str, title: &str) -> bool {
+        self.artist.to_lowercase() == artist.to_lowercase() && self.title.to_lowercase() == title.to_lowercase()
+    }
+}
+
+/// Favorites are keyed by artist and title, so the same song is liked on
+/// every player. Tracks without a title cannot be liked.
+fn key(track: &TrackInfo) -> Option<(String, String)> {
+    let title = track.title.clone()?;
+    Some((track.artist_display().unwrap_or_default(), title))
+}
+
+/// Liked tracks, stored as JSON and written on every change.
+#[derive(Debug, Default)]
+pub struct FavoritesStore {
+    path: Option<PathBuf>,
+    favorites: Vec<Favorite>,
+}
+
+impl FavoritesStore {
+    /// Load the store from `path`; a missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            favorites: json_store::load(path, "favorites file")?,
+        })
+    }
+
+    /// Newest first
+    pub fn list(&self) -> impl Iterator<Item = &Favorite> {
+        self.favorites.iter().rev()
+    }
+
+    pub fn contains(&self, track: &TrackInfo) -> bool {
+        key(track).is_some_and(|(artist, title)| self.favorites.iter().any(|f| f.matches(&artist, &title)))
+    }
+
+    /// Like `track`, or unlike it if it is already liked. Returns whether it is liked now.
+    pub fn toggle(&mut self, track: &TrackInfo, player: &str) -> bool {
+        let Some((artist, title)) = key(track) else {
+            return false;
+        };
+        let liked = if let Some(index) = self.favorites.iter().position(|f| f.matches(&artist, &title)) {
+            self.favorites.remove(index);
+            false
+        } else {
+            let liked_at = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+            self.favorites.push(Favorite {
+                artist,
+                title,
+                album: track.album.clone(),
+                player: player.to_string(),
+                liked_at,
+            });
+            true
+        };
+        self.save();
+        liked
+    }
+
+    fn save(&self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+        match json_store::save_atomically(path, &self.favorites) {
+            Ok(()) => info!("Saved {} favorites", self.favorites.len()),
+            Err(e) => error!("Failed to save favorites: {:#}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(artist: &str, title: &str) -> TrackInfo {
+        TrackInfo {
+            title: Some(title.to_string()),
+            artists: vec![artist.to_string()],
+            ..TrackInfo::default()
+        }
+    }
+
+    #[test]
+    fn toggles_and_persists_favorites() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("favorites.json");
+
+        let mut store = FavoritesStore::load(&path).unwrap();
+        assert!(store.toggle(&track("Artist", "Song"), "Spotify"));
+        assert!(store.toggle(&track("Other", "Tune"), "mpv"));
+
+        let mut store = FavoritesStore::load(&path).unwrap();
+        // Matching ignores case, and the player the track is playing on
+        assert!(store.contains(&track("artist", "SONG")));
+        let players: Vec<&str> = store.list().map(|f| f.player.as_str()).collect();
+        assert_eq!(players, vec!["mpv", "Spotify"]);
+
+        assert!(!store.toggle(&track("Artist", "Song"), "mpv"));
+        assert!(!store.contains(&track("Artist", "Song")));
+    }
+
+    #[test]
+    fn tracks_without_title_cannot_be_liked() {
+        let mut store = FavoritesStore::default();
+        assert!(!store.toggle(&TrackInfo::default(), "Spotify"));
+        assert_eq!(store.list().count(), 0);
+    }
+}
@@ -0,0 +1,433 @@
+use log::warn;
+use serde::{Deserialize, Serialize};
+use std::{env, fmt};
+
+/// Language of the UI
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Language {
+    #[default]
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "zh_CN")]
+    SimplifiedChinese,
+}
+
+impl Language {
+    /// The language of the locale in `LC_ALL`, `LC_MESSAGES` or `LANG`,
+    /// the first one that is set wins like for gettext
+    pub fn detect() -> Self {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .into_iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty());
+        locale.map_or(Language::English, |locale| Self::from_locale(&locale))
+    }
+
+    /// The language of a locale like "zh_CN.UTF-8"; English unless we have a translation
+    fn from_locale(locale: &str) -> Self {
+        let language = locale.split(['_', '-', '.', '@']).next().unwrap_or_default();
+        match language.to_lowercase().as_str() {
+            // Only Simplified Chinese is translated, which beats English for every Chinese locale
+            "zh" => Language::SimplifiedChinese,
+            _ => Language::English,
+        }
+    }
+
+    fn messages(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Language::English => EN,
+            Language::SimplifiedChinese => ZH_CN,
+        }
+    }
+}
+
+/// UI strings in the chosen language. Strings with `{name}` placeholders
+/// are filled in with `format`, so translations can reorder them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Catalog {
+    language: Language,
+}
+
+impl Catalog {
+    pub fn new(language: Language) -> Self {
+        Self { language }
+    }
+
+    pub fn get(self, key: &str) -> &'static str {
+        let find = |language: Language| {
+            language.messages().iter().find(|(k, _)| *k == key).map(|(_, message)| *message)
+        };
+        find(self.language).or_else(|| find(Language::English)).unwrap_or_else(|| {
+            warn!("No message for {:?}", key);
+            ""
+        })
+    }
+
+    /// The message for `key` with its placeholders replaced by `args`
+    pub fn format(self, key: &str, args: &[(&str, &str)]) -> String {
+        args.iter().fold(self.get(key).to_string(), |message, (name, value)| {
+            message.replace(&format!("{{{}}}", name), value)
+        })
+    }
+}
+
+/// A UI string made before it is known which language shows it, e.g. a
+/// status message from `MusicPlayer`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    key: &'static str,
+    args: Vec<(&'static str, Arg)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Arg {
+    Text(String),
+    /// Translated too, like the name of a command
+    Key(&'static str),
+}
+
+impl Message {
+    pub fn new(key: &'static str) -> Self {
+        Self { key, args: Vec::new() }
+    }
+
+    pub fn arg(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.args.push((name, Arg::Text(value.into())));
+        self
+    }
+
+    /// An argument that is a message key itself
+    pub fn arg_key(mut self, name: &'static str, key: &'static str) -> Self {
+        self.args.push((name, Arg::Key(key)));
+        self
+    }
+}
+
+impl Catalog {
+    pub fn message(self, message: &Message) -> String {
+        let args: Vec<(&str, &str)> = message
+            .args
+            .iter()
+            .map(|(name, value)| match value {
+                Arg::Text(text) => (*name, text.as_str()),
+                Arg::Key(key) => (*name, self.get(key)),
+            })
+            .collect();
+        self.format(message.key, &args)
+    }
+}
+
+/// In English, for logs and errors outside the UI
+impl fmt::Display for Message {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&Catalog::default().message(self))
+    }
+}
+
+impl std::error::Error for Message {}
+
+const EN: &[(&str, &str)] = &[
+    ("app.title", "♪ Music Tray"),
+    ("app.exclusive", "♪ Music Tray [exclusive playback]"),
+    ("log.title", "Log (L to hide)"),
+    ("button.previous", "<< Previous"),
+    ("button.play", "> Play"),
+    ("button.pause", "|| Pause"),
+    ("button.stop", "[] Stop"),
+    ("button.next", "Next >>"),
+    ("button.quit", "X Quit"),
+    ("key.previous", "P"),
+    ("key.play_pause", "SPACE"),
+    ("key.stop", "S"),
+    ("key.next", "N"),
+    ("key.quit", "Q"),
+    ("key.like", "F"),
+    ("cover.title", "Cover Art (Z: zoom)"),
+    ("cover.status", "{status}, C: retry"),
+    ("cover.none", "No Cover\nAvailable"),
+    ("cover.loading", "Loading Cover..."),
+    ("cover.failed", "Failed to Load\nCover Image"),
+    ("cover.detecting", "Detecting\nImage Protocol..."),
+    ("cover.undetected", "not detected yet"),
+    ("cover.protocol", "{protocol} ({reason})"),
+    ("cover.protocol_in", "{protocol} ({reason}, in {multiplexer})"),
+    ("cover.protocol_blocked", "{protocol} ({reason}, in {multiplexer} without passthrough)"),
+    ("cover.reason.detected", "detected"),
+    ("cover.reason.query_failed", "terminal query failed"),
+    ("cover.reason.not_queried", "not queried"),
+    ("cover.reason.configured", "configured"),
+    ("protocol.auto", "auto"),
+    ("protocol.kitty", "kitty"),
+    ("protocol.sixel", "sixel"),
+    ("protocol.iterm2", "iTerm2"),
+    ("protocol.halfblocks", "half blocks"),
+    ("protocol.ascii", "text art"),
+    ("zoom.close", "Z/Esc: close"),
+    ("open.title", "Open URL or file (Enter: open, TAB: complete, Esc: cancel)"),
+    ("quit.title", "Quit player"),
+    ("quit.question", "Close {player}?"),
+    ("quit.yes", "[Y]es"),
+    ("quit.no", "[N]o"),
+    ("status.title", "Status (W: raise window, Shift-Q: quit player)"),
+    (
+        "status.no_bus",
+        "[-] No D-Bus session bus, retrying... ({reason})\n    \
+         Check DBUS_SESSION_BUS_ADDRESS is set (e.g. over SSH or in a TTY)",
+    ),
+    ("status.connected", "[+] Connected to: {player}"),
+    ("status.no_player", "[+] Connected (No active player)"),
+    ("status.volume", "{player} (volume {volume}%)"),
+    ("status.resume", "[R] Resume \"{title}\" at {position} (Esc to dismiss)"),
+    ("status.local_cover", "[F] Cover: {url}"),
+    ("status.web_cover", "[W] Cover: {url}"),
+    ("status.nothing_to_like", "Nothing to like"),
+    ("status.liked", "Added \"{title}\" to favorites"),
+    ("status.unliked", "Removed \"{title}\" from favorites"),
+    ("status.unsupported", "{player} does not support {command}"),
+    ("status.rejected", "{player} rejected {command}"),
+    ("status.opening", "Opening {uri}"),
+    ("status.no_volume", "The player has no volume control"),
+    ("open.no_player", "No player to open it in"),
+    ("open.empty", "Nothing to open"),
+    ("open.missing", "{path} does not exist"),
+    ("open.not_uri", "{uri} is not a URI"),
+    ("open.no_uris", "The player does not open URIs"),
+    ("open.scheme", "The player does not support {scheme}: URIs (only {supported})"),
+    ("open.failed", "Could not open it: {error}"),
+    ("command.play_pause", "play/pause"),
+    ("command.play", "play"),
+    ("command.pause", "pause"),
+    ("command.stop", "stop"),
+    ("command.next", "next track"),
+    ("command.previous", "previous track"),
+    ("command.seek", "seeking"),
+    ("command.rate", "changing speed"),
+    ("command.raise", "raising its window"),
+    ("command.quit", "quitting"),
+    ("command.open_uri", "opening URIs"),
+    ("command.volume", "changing volume"),
+    ("command.rating", "rating"),
+    ("track.title", "Track (I: details)"),
+    ("artist.title", "Artist"),
+    ("album.title", "Album"),
+    ("progress.title", "Progress ({progress})"),
+    ("progress.live", "LIVE"),
+    ("details.title", "Details (Up/Down to scroll)"),
+    ("unknown.title", "Unknown"),
+    ("unknown.artist", "Unknown Artist"),
+    ("unknown.album", "Unknown Album"),
+    ("favorites.title", "Favorites (Shift-F: back, F: like current track)"),
+    ("favorites.heard_on", "heard on {player}"),
+    ("favorites.empty", "No favorites yet, press F to like the current track"),
+    ("dashboard.title", "Players (D: back, TAB: focus next, O: pause all others)"),
+    ("dashboard.pause_others", "[ Pause all others ]"),
+    ("dashboard.empty", "No players found"),
+    ("dashboard.more", "+{count} more (TAB or click to scroll)"),
+    ("card.playing", "> Playing"),
+    ("card.paused", "|| Paused"),
+    ("detail.title", "Title"),
+    ("detail.artists", "Artists"),
+    ("detail.album", "Album"),
+    ("detail.album_artists", "Album artists"),
+    ("detail.track", "Track"),
+    ("detail.disc", "Disc"),
+    ("detail.genre", "Genre"),
+    ("detail.year", "Year"),
+    ("detail.composer", "Composer"),
+    ("detail.url", "URL"),
+    ("detail.track_id", "Track ID"),
+    ("detail.rating", "Rating"),
+    ("detail.play_count", "Play count"),
+];
+
+const ZH_CN: &[(&str, &str)] = &[
+    ("app.title", "♪ Music Tray"),
+    ("app.exclusive", "♪ Music Tray [独占播放]"),
+    ("log.title", "日志（L 隐藏）"),
+    ("button.previous", "<< 上一首"),
+    ("button.play", "> 播放"),
+    ("button.pause", "|| 暂停"),
+    ("button.stop", "[] 停止"),
+    ("button.next", "下一首 >>"),
+    ("button.quit", "X 退出"),
+    ("key.previous", "P"),
+    ("key.play_pause", "空格"),
+    ("key.stop", "S"),
+    ("key.next", "N"),
+    ("key.quit", "Q"),
+    ("key.like", "F"),
+    ("cover.title", "封面（Z：放大）"),
+    ("cover.status", "{status}，C：重试"),
+    ("cover.none", "暂无封面"),
+    ("cover.loading", "正在加载封面..."),
+    ("cover.failed", "封面\n加载失败"),
+    ("cover.detecting", "正在检测\n图像协议..."),
+    ("cover.undetected", "尚未检测"),
+    ("cover.protocol", "{protocol}（{reason}）"),
+    ("cover.protocol_in", "{protocol}（{reason}，在 {multiplexer} 中）"),
+    ("cover.protocol_blocked", "{protocol}（{reason}，在 {multiplexer} 中且没有透传）"),
+    ("cover.reason.detected", "已检测"),
+    ("cover.reason.query_failed", "终端查询失败"),
+    ("cover.reason.not_queried", "未查询终端"),
+    ("cover.reason.configured", "已配置"),
+    ("protocol.auto", "自动"),
+    ("protocol.kitty", "kitty"),
+    ("protocol.sixel", "sixel"),
+    ("protocol.iterm2", "iTerm2"),
+    ("protocol.halfblocks", "半块字符"),
+    ("protocol.ascii", "文本绘制"),
+    ("zoom.close", "Z/Esc：关闭"),
+    ("open.title", "打开网址或文件（Enter：打开，TAB：补全，Esc：取消）"),
+    ("quit.title", "退出播放器"),
+    ("quit.question", "关闭 {player}？"),
+    ("quit.yes", "[Y] 是"),
+    ("quit.no", "[N] 否"),
+    ("status.title", "状态（W：显示窗口，Shift-Q：退出播放器）"),
+    (
+        "status.no_bus",
+        "[-] 没有 D-Bus 会话总线，正在重试...（{reason}）\n    \
+         请检查是否设置了 DBUS_SESSION_BUS_ADDRESS（例如通过 SSH 或在 TTY 中运行时）",
+    ),
+    ("status.connected", "[+] 已连接：{player}"),
+    ("status.no_player", "[+] 已连接（没有活动的播放器）"),
+    ("status.volume", "{player}（音量 {volume}%）"),
+    ("status.resume", "[R] 从 {position} 继续播放“{title}”（Esc 忽略）"),
+    ("status.local_cover", "[F] 封面：{url}"),
+    ("status.web_cover", "[W] 封面：{url}"),
+    ("status.nothing_to_like", "没有可收藏的曲目"),
+    ("status.liked", "已将“{title}”加入收藏"),
+    ("status.unliked", "已将“{title}”移出收藏"),
+    ("status.unsupported", "{player} 不支持{command}"),
+    ("status.rejected", "{player} 拒绝了{command}"),
+    ("status.opening", "正在打开 {uri}"),
+    ("status.no_volume", "该播放器无法调节音量"),
+    ("open.no_player", "没有可以打开它的播放器"),
+    ("open.empty", "没有要打开的内容"),
+    ("open.missing", "{path} 不存在"),
+    ("open.not_uri", "{uri} 不是 URI"),
+    ("open.no_uris", "该播放器不能打开 URI"),
+    ("open.scheme", "该播放器不支持 {scheme}: URI（仅支持 {supported}）"),
+    ("open.failed", "无法打开：{error}"),
+    ("command.play_pause", "播放/暂停"),
+    ("command.play", "播放"),
+    ("command.pause", "暂停"),
+    ("command.stop", "停止"),
+    ("command.next", "下一首"),
+    ("command.previous", "上一首"),
+    ("command.seek", "跳转"),
+    ("command.rate", "调节速度"),
+    ("command.raise", "显示窗口"),
+    ("command.quit", "退出"),
+    ("command.open_uri", "打开 URI"),
+    ("command.volume", "调节音量"),
+    ("command.rating", "评分"),
+    ("track.title", "曲目（I：详情）"),
+    ("artist.title", "艺术家"),
+    ("album.title", "专辑"),
+    ("progress.title", "进度（{progress}）"),
+    ("progress.live", "直播"),
+    ("details.title", "详情（上/下键滚动）"),
+    ("unknown.title", "未知"),
+    ("unknown.artist", "未知艺术家"),
+    ("unknown.album", "未知专辑"),
+    ("favorites.title", "收藏（Shift-F：返回，F：收藏当前曲目）"),
+    ("favorites.heard_on", "播放于 {player}"),
+    ("favorites.empty", "还没有收藏，按 F 收藏当前曲目"),
+    ("dashboard.title", "播放器（D：返回，TAB：切换焦点，O：暂停其他播放器）"),
+    ("dashboard.pause_others", "[ 暂停其他播放器 ]"),
+    ("dashboard.empty", "未找到播放器"),
+    ("dashboard.more", "还有 {count} 个（TAB 或点击滚动）"),
+    ("card.playing", "> 播放中"),
+    ("card.paused", "|| 已暂停"),
+    ("detail.title", "标题"),
+    ("detail.artists", "艺术家"),
+    ("detail.album", "专辑"),
+    ("detail.album_artists", "专辑艺术家"),
+    ("detail.track", "曲目号"),
+    ("detail.disc", "碟号"),
+    ("detail.genre", "流派"),
+    ("detail.year", "年份"),
+    ("detail.composer", "作曲"),
+    ("detail.url", "网址"),
+    ("detail.track_id", "曲目 ID"),
+    ("detail.rating", "评分"),
+    ("detail.play_count", "播放次数"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    const LANGUAGES: [Language; 2] = [Language::English, Language::SimplifiedChinese];
+
+    /// The `{name}` placeholders in `message`
+    fn placeholders(message: &str) -> BTreeSet<&str> {
+        message
+            .split('{')
+            .skip(1)
+            .filter_map(|rest| rest.split_once('}').map(|(name, _)| name))
+            .collect()
+    }
+
+    #[test]
+    fn every_key_exists_in_every_language() {
+        let keys = |language: Language| language.messages().iter().map(|(key, _)| *key).collect::<Vec<_>>();
+        let english: BTreeSet<&str> = keys(Language::English).into_iter().collect();
+        for language in LANGUAGES {
+            let language_keys = keys(language);
+            let unique: BTreeSet<&str> = language_keys.iter().copied().collect();
+            assert_eq!(unique.len(), language_keys.len(), "{:?} has duplicate keys", language);
+            assert_eq!(unique, english, "{:?} does not have the English keys", language);
+            for (key, message) in language.messages() {
+                assert!(!message.is_empty(), "{:?} {} is empty", language, key);
+                assert_eq!(
+                    placeholders(message),
+                    placeholders(Catalog::default().get(key)),
+                    "{:?} {} has other placeholders",
+                    language,
+                    key
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn language_comes_from_the_locale() {
+        assert_eq!(Language::from_locale("zh_CN.UTF-8"), Language::SimplifiedChinese);
+        assert_eq!(Language::from_locale("zh_TW"), Language::SimplifiedChinese);
+        assert_eq!(Language::from_locale("zh"), Language::SimplifiedChinese);
+        assert_eq!(Language::from_locale("en_US.UTF-8"), Language::English);
+        assert_eq!(Language::from_locale("de_DE@euro"), Language::English);
+        assert_eq!(Language::from_locale("C"), Language::English);
+        assert_eq!(Language::from_locale("POSIX"), Language::English);
+    }
+
+    #[test]
+    fn messages_are_filled_in() {
+        let chinese = Catalog::new(Language::SimplifiedChinese);
+        assert_eq!(chinese.get("unknown.artist"), "未知艺术家");
+        assert_eq!(
+            chinese.format("status.resume", &[("title", "晴天"), ("position", "00:01:05")]),
+            "[R] 从 00:01:05 继续播放“晴天”（Esc 忽略）"
+        );
+        assert_eq!(Catalog::default().format("quit.question", &[("player", "mpv")]), "Close mpv?");
+        assert_eq!(Catalog::default().get("no.such.key"), "");
+    }
+
+    #[test]
+    fn messages_translate_their_arguments() {
+        let message = Message::new("status.unsupported").arg("player", "mpv").arg_key("command", "command.next");
+        assert_eq!(Catalog::default().message(&message), "mpv does not support next track");
+        assert_eq!(Catalog::new(Language::SimplifiedChinese).message(&message), "mpv 不支持下一首");
+        assert_eq!(message.to_string(), "mpv does not support next track");
+    }
+
+    #[test]
+    fn language_can_be_configured() {
+        let config: crate::config::Config = toml::from_str("language = \"zh_CN\"").unwrap();
+        assert_eq!(config.language, Some(Language::SimplifiedChinese));
+        let config: crate::config::Config = toml::from_str("language = \"en\"").unwrap();
+        assert_eq!(config.language, Some(Language::English));
+    }
+}
@@ -0,0 +1,47 @@
+use anyhow::{Context, Result};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{fs, io::ErrorKind, path::Path};
+
+/// Read `path` as JSON; a missing file is the default value. `what` names
+/// the file in errors, e.g. "favorites file".
+pub fn load<T: DeserializeOwned + Default>(path: &Path, what: &str) -> Result<T> {
+    match fs::read_to_string(path) {
+        Ok(contents) => {
+            serde_json::from_str(&contents).with_context(|| format!("Invalid {} {}", what, path.display()))
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(T::default()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+/// Write `value` to `path` as JSON, creating its directory
+pub fn save_atomically<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Write to a temporary file first so a crash never leaves a truncated store
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, serde_json::to_string_pretty(value)?)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_files_are_empty_and_bad_ones_fail() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("store").join("items.json");
+        assert_eq!(load::<Vec<u32>>(&path, "store").unwrap(), Vec::<u32>::new());
+
+        save_atomically(&path, &vec![1, 2]).unwrap();
+        assert_eq!(load::<Vec<u32>>(&path, "store").unwrap(), vec![1, 2]);
+        assert!(!path.with_extension("json.tmp").exists());
+
+        fs::write(&path, "not json").unwrap();
+        let error = load::<Vec<u32>>(&path, "store").unwrap_err();
+        assert!(error.to_string().starts_with("Invalid store"));
+    }
+}
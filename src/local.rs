@@ -0,0 +1,710 @@
+use anyhow::{anyhow, bail, Context, Result};
+use log::{error, info, warn};
+use mpris::{Metadata, MetadataValue, PlaybackStatus};
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender, TryRecvError},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+use symphonia::core::{
+    audio::SampleBuffer,
+    codecs::{Decoder, DecoderOptions, CODEC_TYPE_NULL},
+    errors::Error as SymphoniaError,
+    formats::{FormatOptions, FormatReader, SeekMode, SeekTo},
+    io::MediaSourceStream,
+    meta::{MetadataOptions, StandardTagKey, Tag},
+    probe::Hint,
+    units::{Time, TimeBase},
+};
+
+use crate::backend::{Capabilities, PlaybackRate, PlayerBackend, PlayerCommand, PlayerEntry};
+use crate::config::LocalConfig;
+use crate::uri;
+
+/// Bus-name style id of the built-in player
+pub const LOCAL_PLAYER_ID: &str = "music-tray.local";
+
+/// Files picked up when playing a directory
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "flac", "ogg", "oga", "wav", "mka", "webm"];
+
+/// `Previous` restarts the track instead when this far into it
+const RESTART_THRESHOLD: Duration = Duration::from_secs(3);
+
+/// The local path of a `file://` URI
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    urlencoding::decode(path).ok().map(|path| PathBuf::from(path.into_owned()))
+}
+
+/// Where decoded audio goes.
+pub trait Sink: Send {
+    /// Interleaved 16-bit samples
+    fn write(&mut self, samples: &[i16], sample_rate: u32, channels: usize) -> Result<()>;
+}
+
+/// Discards audio, optionally taking as long as playing it would.
+pub struct NullSink {
+    pub paced: bool,
+}
+
+impl Sink for NullSink {
+    fn write(&mut self, samples: &[i16], sample_rate: u32, channels: usize) -> Result<()> {
+        if self.paced && sample_rate > 0 && channels > 0 {
+            let frames = samples.len() / channels;
+            thread::sleep(Duration::from_secs_f64(frames as f64 / sample_rate as f64));
+        }
+        Ok(())
+    }
+}
+
+/// Writes raw signed 16-bit little-endian PCM to a file or FIFO, e.g. for
+/// `pw-cat --playback --format s16 -` or `aplay -f S16_LE`.
+pub struct FileSink {
+    file: BufWriter<File>,
+    format: Option<(u32, usize)>,
+}
+
+impl FileSink {
+    pub fn create(path: &Path) -> Result<Self> {
+        let file = File::create(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        Ok(Self {
+            file: BufWriter::new(file),
+            format: None,
+        })
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&mut self, samples: &[i16], sample_rate: u32, channels: usize) -> Result<()> {
+        if self.format != Some((sample_rate, channels)) {
+            info!("Writing {} Hz, {} channel audio", sample_rate, channels);
+            self.format = Some((sample_rate, channels));
+        }
+        for sample in samples {
+            self.file.write_all(&sample.to_le_bytes())?;
+        }
+        self.file.flush()?;
+        Ok(())
+    }
+}
+
+/// The sink named in the config: "null" or "file:<path>"
+pub fn sink_from_config(name: &str) -> Result<Box<dyn Sink>> {
+    match name {
+        "null" => Ok(Box::new(NullSink { paced: true })),
+        _ => match name.strip_prefix("file:") {
+            Some(path) => Ok(Box::new(FileSink::create(&uri::expand_home(path))?)),
+            None => bail!("Unknown audio sink {:?}, use \"null\" or \"file:<path>\"", name),
+        },
+    }
+}
+
+/// The files to play for `path`: a directory (searched recursively), an
+/// M3U playlist or a single file.
+pub fn load_playlist(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    if path.is_dir() {
+        collect_audio_files(path, &mut files)?;
+        files.sort();
+    } else if path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("m3u") || ext.eq_ignore_ascii_case("m3u8"))
+    {
+        let contents = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let base = path.parent().unwrap_or(Path::new("."));
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            files.push(file_path(line).unwrap_or_else(|| base.join(line)));
+        }
+    } else if path.is_file() {
+        files.push(path.to_path_buf());
+    } else {
+        bail!("{} does not exist", path.display());
+    }
+
+    if files.is_empty() {
+        bail!("No audio files in {}", path.display());
+    }
+    Ok(files)
+}
+
+fn collect_audio_files(dir: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_audio_files(&path, files)?;
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| AUDIO_EXTENSIONS.iter().any(|known| ext.eq_ignore_ascii_case(known)))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// What the playback thread publishes for the backend to read
+#[derive(Debug, Clone)]
+struct Snapshot {
+    status: PlaybackStatus,
+    position: Duration,
+    metadata: Metadata,
+}
+
+/// An opened file, ready to decode
+struct OpenTrack {
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn Decoder>,
+    track_id: u32,
+    time_base: Option<TimeBase>,
+    position: Duration,
+    metadata: Metadata,
+}
+
+impl OpenTrack {
+    fn open(path: &Path, index: usize) -> Result<Self> {
+        let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        let stream = MediaSourceStream::new(Box::new(file), Default::default());
+        let mut hint = Hint::new();
+        if let Some(ext) = path.extension().and_then(|ext| ext.to_str()) {
+            hint.with_extension(ext);
+        }
+        let mut probed = symphonia::default::get_probe()
+            .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+            .with_context(|| format!("Unsupported file {}", path.display()))?;
+
+        let mut format = probed.format;
+        let track = format
+            .tracks()
+            .iter()
+            .find(|track| track.codec_params.codec != CODEC_TYPE_NULL)
+            .ok_or_else(|| anyhow!("No audio in {}", path.display()))?;
+        let track_id = track.id;
+        let time_base = track.codec_params.time_base;
+        let length = track
+            .codec_params
+            .n_frames
+            .zip(time_base)
+            .map(|(frames, time_base)| time_base.calc_time(frames));
+        let decoder = symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+        // Tags can be in the container (e.g. Vorbis comments) or in front of it (ID3)
+        let mut tags: Vec<Tag> = Vec::new();
+        if let Some(revision) = probed.metadata.get().as_ref().and_then(|m| m.current()) {
+            tags.extend(revision.tags().iter().cloned());
+        }
+        if let Some(revision) = format.metadata().current() {
+            tags.extend(revision.tags().iter().cloned());
+        }
+        let tag = |key: StandardTagKey| {
+            tags.iter()
+                .find(|tag| tag.std_key == Some(key))
+                .map(|tag| tag.value.to_string())
+        };
+
+        let mut values: HashMap<String, MetadataValue> = HashMap::new();
+        values.insert("mpris:trackid".to_string(), format!("/org/musictray/track/{}", index).into());
+        let title = tag(StandardTagKey::TrackTitle).unwrap_or_else(|| {
+            path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default()
+        });
+        values.insert("xesam:title".to_string(), title.into());
+        if let Some(artist) = tag(StandardTagKey::Artist) {
+            values.insert("xesam:artist".to_string(), vec![MetadataValue::from(artist)].into());
+        }
+        if let Some(album) = tag(StandardTagKey::Album) {
+            values.insert("xesam:album".to_string(), album.into());
+        }
+        if let Some(length) = length {
+            let micros = length.seconds as f64 * 1_000_000.0 + length.frac * 1_000_000.0;
+            values.insert("mpris:length".to_string(), (micros as i64).into());
+        }
+        if let Ok(path) = path.canonicalize() {
+            values.insert("xesam:url".to_string(), uri::file_uri(&path).into());
+        }
+
+        Ok(Self {
+            format,
+            decoder,
+            track_id,
+            time_base,
+            position: Duration::ZERO,
+            metadata: values.into(),
+        })
+    }
+
+    /// Decode the next packet into `sink`. Returns `false` at the end of the track.
+    fn play_packet(&mut self, sink: &mut dyn Sink) -> Result<bool> {
+        loop {
+            let packet = match self.format.next_packet() {
+                Ok(packet) => packet,
+                Err(SymphoniaError::IoError(e)) if e.kind() == ErrorKind::UnexpectedEof => return Ok(false),
+                Err(e) => return Err(e.into()),
+            };
+            if packet.track_id() != self.track_id {
+                continue;
+            }
+            let decoded = match self.decoder.decode(&packet) {
+                Ok(decoded) => decoded,
+                // A corrupt packet is skipped, not the whole track
+                Err(SymphoniaError::DecodeError(e)) => {
+                    warn!("Skipping undecodable packet: {}", e);
+                    continue;
+                }
+                Err(e) => return Err(e.into()),
+            };
+
+            let spec = *decoded.spec();
+            let mut samples = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+            samples.copy_interleaved_ref(decoded);
+            sink.write(samples.samples(), spec.rate, spec.channels.count())?;
+
+            if let Some(time_base) = self.time_base {
+                let time = time_base.calc_time(packet.ts() + packet.dur());
+                self.position = Duration::from_secs(time.seconds) + Duration::from_secs_f64(time.frac);
+            }
+            return Ok(true);
+        }
+    }
+
+    fn seek(&mut self, position: Duration) -> Result<()> {
+        let time = Time::new(position.as_secs(), position.subsec_nanos() as f64 / 1e9);
+        self.format.seek(
+            SeekMode::Coarse,
+            SeekTo::Time {
+                time,
+                track_id: Some(self.track_id),
+            },
+        )?;
+        self.decoder.reset();
+        self.position = position;
+        Ok(())
+    }
+}
+
+/// Playback state, owned by the playback thread.
+struct Engine {
+    playlist: Vec<PathBuf>,
+    index: usize,
+    status: PlaybackStatus,
+    current: Option<OpenTrack>,
+    sink: Box<dyn Sink>,
+    snapshot: Arc<Mutex<Snapshot>>,
+}
+
+impl Engine {
+    fn new(playlist: Vec<PathBuf>, sink: Box<dyn Sink>, snapshot: Arc<Mutex<Snapshot>>) -> Self {
+        let mut engine = Self {
+            playlist,
+            index: 0,
+            status: PlaybackStatus::Stopped,
+            current: None,
+            sink,
+            snapshot,
+        };
+        engine.load(0);
+        engine
+    }
+
+    fn is_playing(&self) -> bool {
+        self.status == PlaybackStatus::Playing
+    }
+
+    /// Open the track at `index`, skipping files that cannot be opened
+    fn load(&mut self, index: usize) {
+        self.current = None;
+        for offset in 0..self.playlist.len() {
+            let index = (index + offset) % self.playlist.len();
+            match OpenTrack::open(&self.playlist[index], index) {
+                Ok(track) => {
+                    self.index = index;
+                    self.current = Some(track);
+                    break;
+                }
+                Err(e) => error!("{:#}", e),
+            }
+        }
+        if self.current.is_none() {
+            self.status = PlaybackStatus::Stopped;
+        }
+        self.publish();
+    }
+
+    fn handle(&mut self, command: PlayerCommand) -> Result<()> {
+        match command {
+            PlayerCommand::Play => self.play(),
+            PlayerCommand::Pause => self.pause(),
+            PlayerCommand::PlayPause if self.is_playing() => self.pause(),
+            PlayerCommand::PlayPause => self.play(),
+            PlayerCommand::Stop => {
+                self.status = PlaybackStatus::Stopped;
+                self.load(self.index);
+            }
+            PlayerCommand::Next => self.skip_to(self.index + 1),
+            PlayerCommand::Previous => {
+                let position = self.current.as_ref().map_or(Duration::ZERO, |track| track.position);
+                if position > RESTART_THRESHOLD {
+                    self.load(self.index);
+                } else {
+                    self.skip_to((self.index + self.playlist.len() - 1) % self.playlist.len());
+                }
+            }
+            PlayerCommand::SetPosition { track_id, position } => {
+                // Ignore seeks meant for a track that has already changed
+                if self.track_id().as_deref() == Some(track_id.as_str()) {
+                    if let Some(track) = self.current.as_mut() {
+                        track.seek(position)?;
+                    }
+                    self.publish();
+                }
+            }
+            PlayerCommand::OpenUri(uri) => {
+                let path = file_path(&uri).ok_or_else(|| anyhow!("Not a file URI: {}", uri))?;
+                self.playlist.push(path);
+                self.skip_to(self.playlist.len() - 1);
+                self.play();
+            }
+            PlayerCommand::SetRate(_)
+            | PlayerCommand::SetVolume(_)
+            | PlayerCommand::SetRating(_)
+            | PlayerCommand::Raise
+            | PlayerCommand::Quit => {
+                bail!("The built-in player does not support {:?}", command)
+            }
+        }
+        Ok(())
+    }
+
+    fn play(&mut self) {
+        if self.current.is_some() {
+            self.status = PlaybackStatus::Playing;
+        }
+        self.publish();
+    }
+
+    fn pause(&mut self) {
+        if self.is_playing() {
+            self.status = PlaybackStatus::Paused;
+        }
+        self.publish();
+    }
+
+    /// Go to `index`, keeping on playing if playing; wraps past the end
+    fn skip_to(&mut self, index: usize) {
+        self.load(index % self.playlist.len());
+    }
+
+    fn track_id(&self) -> Option<String> {
+        let track = self.current.as_ref()?;
+        track.metadata.get("mpris:trackid")?.as_str().map(|id| id.to_string())
+    }
+
+    /// Play one packet; at the end of a track move on, stopping after the last one
+    fn step(&mut self) {
+        if !self.is_playing() {
+            return;
+        }
+        let Some(track) = self.current.as_mut() else {
+            return;
+        };
+        match track.play_packet(self.sink.as_mut()) {
+            Ok(true) => {}
+            Ok(false) => {
+                if self.index + 1 < self.playlist.len() {
+                    self.load(self.index + 1);
+                } else {
+                    info!("End of playlist");
+                    self.status = PlaybackStatus::Stopped;
+                    self.load(0);
+                }
+            }
+            Err(e) => {
+                error!("Playback of {} failed: {:#}", self.playlist[self.index].display(), e);
+                self.status = PlaybackStatus::Stopped;
+            }
+        }
+        self.publish();
+    }
+
+    fn publish(&self) {
+        let mut snapshot = self.snapshot.lock().unwrap();
+        snapshot.status = self.status;
+        snapshot.position = self.current.as_ref().map_or(Duration::ZERO, |track| track.position);
+        snapshot.metadata = self
+            .current
+            .as_ref()
+            .map(|track| track.metadata.clone())
+            .unwrap_or_default();
+    }
+}
+
+/// Runs commands and plays packets until the backend is dropped
+fn run(mut engine: Engine, commands: Receiver<PlayerCommand>) {
+    loop {
+        // Only block while there is nothing to play
+        let command = if engine.is_playing() {
+            match commands.try_recv() {
+                Ok(command) => Some(command),
+                Err(TryRecvError::Empty) => None,
+                Err(TryRecvError::Disconnected) => break,
+            }
+        } else {
+            match commands.recv() {
+                Ok(command) => Some(command),
+                Err(_) => break,
+            }
+        };
+        if let Some(command) = command {
+            if let Err(e) = engine.handle(command) {
+                error!("{:#}", e);
+            }
+        }
+        engine.step();
+    }
+}
+
+/// The built-in player, exposed like any other player.
+pub struct LocalBackend {
+    snapshot: Arc<Mutex<Snapshot>>,
+    commands: Sender<PlayerCommand>,
+}
+
+impl LocalBackend {
+    /// Start a player for the files in `playlist`
+    pub fn new(playlist: Vec<PathBuf>, sink: Box<dyn Sink>) -> Result<Self> {
+        let snapshot = Arc::new(Mutex::new(Snapshot {
+            status: PlaybackStatus::Stopped,
+            position: Duration::ZERO,
+            metadata: Metadata::default(),
+        }));
+        let engine = Engine::new(playlist, sink, Arc::clone(&snapshot));
+        let (commands, receiver) = mpsc::channel();
+        thread::Builder::new()
+            .name("local-player".to_string())
+            .spawn(move || run(engine, receiver))?;
+        Ok(Self { snapshot, commands })
+    }
+
+    /// A player for the directory or playlist from the config
+    pub fn from_config(config: &LocalConfig, path: &Path) -> Result<Self> {
+        let playlist = load_playlist(path)?;
+        info!("Built-in player: {} files from {}", playlist.len(), path.display());
+        Self::new(playlist, sink_from_config(&config.sink)?)
+    }
+
+    fn check_id(&self, id: &str) -> Result<()> {
+        if id != LOCAL_PLAYER_ID {
+            bail!("Player {} is no longer available", id);
+        }
+        Ok(())
+    }
+
+    fn snapshot(&self, id: &str) -> Result<Snapshot> {
+        self.check_id(id)?;
+        Ok(self.snapshot.lock().unwrap().clone())
+    }
+}
+
+impl PlayerBackend for LocalBackend {
+    fn find_all(&mut self) -> Result<Vec<PlayerEntry>> {
+        Ok(vec![PlayerEntry {
+            id: LOCAL_PLAYER_ID.to_string(),
+            identity: "Music Tray".to_string(),
+        }])
+    }
+
+    fn playback_status(&self, id: &str) -> Result<PlaybackStatus> {
+        Ok(self.snapshot(id)?.status)
+    }
+
+    fn metadata(&self, id: &str) -> Result<Metadata> {
+        Ok(self.snapshot(id)?.metadata)
+    }
+
+    fn position(&self, id: &str) -> Result<Duration> {
+        Ok(self.snapshot(id)?.position)
+    }
+
+    fn rate(&self, id: &str) -> Result<Option<PlaybackRate>> {
+        self.check_id(id)?;
+        Ok(None)
+    }
+
+    fn volume(&self, id: &str) -> Result<Option<f64>> {
+        self.check_id(id)?;
+        Ok(None)
+    }
+
+    fn capabilities(&self, id: &str) -> Result<Capabilities> {
+        self.check_id(id)?;
+        Ok(Capabilities {
+            can_raise: false,
+            can_quit: false,
+            ..Capabilities::default()
+        })
+    }
+
+    fn supported_uri_schemes(&self, id: &str) -> Result<Vec<String>> {
+        self.check_id(id)?;
+        Ok(vec!["file".to_string()])
+    }
+
+    fn send(&mut self, id: &str, command: &PlayerCommand) -> Result<()> {
+        self.check_id(id)?;
+        self.commands
+            .send(command.clone())
+            .map_err(|_| anyhow!("The built-in player has stopped"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `seconds` of silence as a 16-bit mono WAV file
+    fn write_wav(path: &Path, seconds: u32) {
+        const RATE: u32 = 8000;
+        let data_len = RATE * seconds * 2;
+        let mut wav = Vec::new();
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        wav.extend_from_slice(&1u16.to_le_bytes()); // Mono
+        wav.extend_from_slice(&RATE.to_le_bytes());
+        wav.extend_from_slice(&(RATE * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.resize(wav.len() + data_len as usize, 0);
+        fs::write(path, wav).unwrap();
+    }
+
+    fn engine(dir: &Path) -> (Engine, Arc<Mutex<Snapshot>>) {
+        write_wav(&dir.join("01 first.wav"), 2);
+        write_wav(&dir.join("02 second.wav"), 1);
+        fs::write(dir.join("notes.txt"), "not audio").unwrap();
+
+        let snapshot = Arc::new(Mutex::new(Snapshot {
+            status: PlaybackStatus::Stopped,
+            position: Duration::ZERO,
+            metadata: Metadata::default(),
+        }));
+        let playlist = load_playlist(dir).unwrap();
+        let engine = Engine::new(playlist, Box::new(NullSink { paced: false }), Arc::clone(&snapshot));
+        (engine, snapshot)
+    }
+
+    fn title(snapshot: &Arc<Mutex<Snapshot>>) -> String {
+        snapshot.lock().unwrap().metadata.title().unwrap().to_string()
+    }
+
+    #[test]
+    fn playlists_from_directories_and_m3u_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_wav(&dir.path().join("b.wav"), 1);
+        write_wav(&dir.path().join("a.wav"), 1);
+        fs::write(dir.path().join("cover.jpg"), b"").unwrap();
+        assert_eq!(
+            load_playlist(dir.path()).unwrap(),
+            vec![dir.path().join("a.wav"), dir.path().join("b.wav")]
+        );
+
+        let m3u = dir.path().join("list.m3u");
+        fs::write(&m3u, "#EXTM3U\nb.wav\n\na.wav\n").unwrap();
+        assert_eq!(
+            load_playlist(&m3u).unwrap(),
+            vec![dir.path().join("b.wav"), dir.path().join("a.wav")]
+        );
+        assert!(load_playlist(&dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn plays_through_the_playlist() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut engine, snapshot) = engine(dir.path());
+        assert_eq!(title(&snapshot), "01 first");
+        assert_eq!(snapshot.lock().unwrap().metadata.length(), Some(Duration::from_secs(2)));
+
+        engine.handle(PlayerCommand::Play).unwrap();
+        engine.step();
+        assert_eq!(snapshot.lock().unwrap().status, PlaybackStatus::Playing);
+        assert!(snapshot.lock().unwrap().position > Duration::ZERO);
+
+        // Pausing stops decoding
+        engine.handle(PlayerCommand::Pause).unwrap();
+        let position = snapshot.lock().unwrap().position;
+        engine.step();
+        assert_eq!(snapshot.lock().unwrap().position, position);
+
+        engine.handle(PlayerCommand::Play).unwrap();
+        while title(&snapshot) == "01 first" {
+            engine.step();
+        }
+        assert_eq!(title(&snapshot), "02 second");
+        while engine.is_playing() {
+            engine.step();
+        }
+        // Stopped at the end, back at the start of the playlist
+        assert_eq!(snapshot.lock().unwrap().status, PlaybackStatus::Stopped);
+        assert_eq!(title(&snapshot), "01 first");
+    }
+
+    #[test]
+    fn skips_and_seeks() {
+        let dir = tempfile::tempdir().unwrap();
+        let (mut engine, snapshot) = engine(dir.path());
+
+        engine.handle(PlayerCommand::Next).unwrap();
+        assert_eq!(title(&snapshot), "02 second");
+        engine.handle(PlayerCommand::Next).unwrap();
+        assert_eq!(title(&snapshot), "01 first");
+        engine.handle(PlayerCommand::Previous).unwrap();
+        assert_eq!(title(&snapshot), "02 second");
+
+        engine.handle(PlayerCommand::Previous).unwrap();
+        let track_id = engine.track_id().unwrap();
+        engine
+            .handle(PlayerCommand::SetPosition {
+                track_id,
+                position: Duration::from_secs(1),
+            })
+            .unwrap();
+        assert_eq!(snapshot.lock().unwrap().position, Duration::from_secs(1));
+        assert!(engine.handle(PlayerCommand::SetRate(2.0)).is_err());
+    }
+
+    #[test]
+    fn backend_reports_the_built_in_player() {
+        let dir = tempfile::tempdir().unwrap();
+        write_wav(&dir.path().join("song.wav"), 1);
+        let mut backend =
+            LocalBackend::new(load_playlist(dir.path()).unwrap(), Box::new(NullSink { paced: true })).unwrap();
+
+        let players = backend.find_all().unwrap();
+        assert_eq!(players[0].id, LOCAL_PLAYER_ID);
+        assert_eq!(backend.metadata(LOCAL_PLAYER_ID).unwrap().title(), Some("song"));
+        assert!(backend.metadata("org.mpris.MediaPlayer2.other").is_err());
+
+        backend.send(LOCAL_PLAYER_ID, &PlayerCommand::Play).unwrap();
+        for _ in 0..100 {
+            if backend.playback_status(LOCAL_PLAYER_ID).unwrap() == PlaybackStatus::Playing {
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("The built-in player did not start");
+    }
+}
@@ -0,0 +1,200 @@
+use anyhow::{Context, Result};
+use log::{info, LevelFilter};
+use std::{
+    collections::VecDeque,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use crate::config::{self, LogConfig};
+
+const LOG_FILE_NAME: &str = "music-tray.log";
+
+/// How many lines the in-app log pane keeps
+const RECENT_LINES: usize = 200;
+
+/// Most recent log lines, shared between the logger and the log pane.
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    lines: Arc<Mutex<VecDeque<String>>>,
+}
+
+impl LogBuffer {
+    fn push(&self, text: &str) {
+        let mut lines = self.lines.lock().unwrap();
+        for line in text.lines().filter(|l| !l.is_empty()) {
+            if lines.len() == RECENT_LINES {
+                lines.pop_front();
+            }
+            lines.push_back(line.to_string());
+        }
+    }
+
+    /// The last `count` lines, oldest first.
+    pub fn last(&self, count: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        lines.iter().skip(lines.len().saturating_sub(count)).cloned().collect()
+    }
+}
+
+/// Log file that is rotated to `<name>.1`, `<name>.2`, ... once it exceeds
+/// `max_size` bytes, keeping at most `keep` old files.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    size: u64,
+    max_size: u64,
+    keep: usize,
+}
+
+impl RotatingFile {
+    pub fn open(path: &Path, max_size: u64, keep: usize) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path: path.to_path_buf(),
+            file,
+            size,
+            max_size,
+            keep,
+        })
+    }
+
+    fn rotated_path(&self, index: usize) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        if self.keep == 0 {
+            fs::remove_file(&self.path)?;
+        } else {
+            // Shift name.N-1 -> name.N, dropping the oldest
+            for index in (1..self.keep).rev() {
+                let from = self.rotated_path(index);
+                if from.exists() {
+                    fs::rename(&from, self.rotated_path(index + 1))?;
+                }
+            }
+            fs::rename(&self.path, self.rotated_path(1))?;
+        }
+
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.size = 0;
+        Ok(())
+    }
+}
+
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.size > 0 && self.size + buf.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.size += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Sends formatted records to the log file and the in-app buffer.
+struct LogWriter {
+    file: RotatingFile,
+    buffer: LogBuffer,
+}
+
+impl Write for LogWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.push(&String::from_utf8_lossy(buf));
+        self.file.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+/// Initialize logging to `$XDG_STATE_HOME/music-tray/music-tray.log`.
+///
+/// The level comes from `cli_level`, then `RUST_LOG`, then the config file.
+pub fn init_logging(config: &LogConfig, cli_level: Option<LevelFilter>) -> Result<LogBuffer> {
+    let dir = config::state_dir();
+    fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+    let path = dir.join(LOG_FILE_NAME);
+    let file = RotatingFile::open(&path, config.max_size_kb * 1024, config.keep)
+        .with_context(|| format!("Failed to open log file {}", path.display()))?;
+
+    let buffer = LogBuffer::default();
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(config.level);
+    if std::env::var_os("RUST_LOG").is_some() {
+        builder.parse_default_env();
+    }
+    if let Some(level) = cli_level {
+        builder.filter_level(level);
+    }
+    builder
+        .target(env_logger::Target::Pipe(Box::new(LogWriter {
+            file,
+            buffer: buffer.clone(),
+        })))
+        .init();
+
+    info!("Music Tray application started, logging to {}", path.display());
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotates_and_keeps_limited_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.log");
+        let mut file = RotatingFile::open(&path, 10, 2).unwrap();
+
+        for line in ["first-line\n", "second-line\n", "third-line\n", "fourth-line\n"] {
+            file.write_all(line.as_bytes()).unwrap();
+        }
+
+        let read = |p: PathBuf| fs::read_to_string(p).unwrap();
+        assert_eq!(read(path.clone()), "fourth-line\n");
+        assert_eq!(read(dir.path().join("test.log.1")), "third-line\n");
+        assert_eq!(read(dir.path().join("test.log.2")), "second-line\n");
+        assert!(!dir.path().join("test.log.3").exists());
+    }
+
+    #[test]
+    fn reopening_continues_existing_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("test.log");
+        fs::write(&path, "0123456789").unwrap();
+
+        let mut file = RotatingFile::open(&path, 12, 1).unwrap();
+        file.write_all(b"abc").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "abc");
+        assert_eq!(fs::read_to_string(dir.path().join("test.log.1")).unwrap(), "0123456789");
+    }
+
+    #[test]
+    fn buffer_keeps_most_recent_lines() {
+        let buffer = LogBuffer::default();
+        for i in 0..RECENT_LINES + 5 {
+            buffer.push(&format!("line {}\n", i));
+        }
+        buffer.push("a\nb\n");
+
+        assert_eq!(buffer.last(2), vec!["a", "b"]);
+        assert_eq!(buffer.last(usize::MAX).len(), RECENT_LINES);
+    }
+}
@@ -0,0 +1,484 @@
+use anyhow::{bail, Context, Result};
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::music::TrackInfo;
+use crate::uri;
+
+/// MusicBrainz asks clients to send at most one request per second
+const REQUEST_INTERVAL: Duration = Duration::from_secs(1);
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+/// Cover file names in a track's folder, without extension
+const FOLDER_NAMES: [&str; 4] = ["cover", "folder", "front", "album"];
+const IMAGE_EXTENSIONS: [&str; 4] = ["jpg", "jpeg", "png", "webp"];
+/// Wait before looking up a cover again after a lookup failed, doubled on
+/// every further failure up to `MAX_RETRY_DELAY`
+const RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30 * 60);
+
+/// Where to look for covers of tracks that have none
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LookupSource {
+    /// An image like cover.jpg next to a local file
+    Folder,
+    /// MusicBrainz to find the album, Cover Art Archive for its cover
+    Musicbrainz,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LookupConfig {
+    /// Tried in order until one has a cover
+    pub sources: Vec<LookupSource>,
+    pub musicbrainz_url: String,
+    pub cover_art_archive_url: String,
+}
+
+impl Default for LookupConfig {
+    fn default() -> Self {
+        Self {
+            sources: vec![LookupSource::Folder],
+            musicbrainz_url: "https://musicbrainz.org".to_string(),
+            cover_art_archive_url: "https://coverartarchive.org".to_string(),
+        }
+    }
+}
+
+/// What a lookup knows about a track
+#[derive(Debug, Clone)]
+struct Query {
+    artist: Option<String>,
+    album: Option<String>,
+    url: Option<String>,
+}
+
+impl Query {
+    fn new(track: &TrackInfo) -> Self {
+        Self {
+            artist: track.album_artists.first().or(track.artists.first()).cloned(),
+            album: track.album.clone(),
+            url: track.url.clone(),
+        }
+    }
+
+    /// Tracks of one album share their cover, other tracks are looked up one by one
+    fn key(&self) -> Option<String> {
+        match (&self.artist, &self.album) {
+            (Some(artist), Some(album)) => Some(format!("{}\n{}", artist, album).to_lowercase()),
+            _ => self.url.clone(),
+        }
+    }
+}
+
+/// Finds covers for tracks without `mpris:artUrl` in the background. Online
+/// results are stored in the cache directory, so each album is fetched once.
+pub struct CoverLookup {
+    config: Arc<LookupConfig>,
+    dir: PathBuf,
+    /// Finished lookups as file:// URLs, `None` if no source has a cover
+    known: HashMap<String, Option<String>>,
+    /// Lookups that failed (e.g. a timeout): how often, and when to try again
+    failed: HashMap<String, (u32, Instant)>,
+    pending: HashSet<String>,
+    sender: Sender<(String, Result<Option<String>>)>,
+    receiver: Receiver<(String, Result<Option<String>>)>,
+    /// When the last online request went out
+    last_request: Arc<Mutex<Option<Instant>>>,
+}
+
+impl CoverLookup {
+    pub fn new(config: LookupConfig, dir: PathBuf) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            config: Arc::new(config),
+            dir,
+            known: HashMap::new(),
+            failed: HashMap::new(),
+            pending: HashSet::new(),
+            sender,
+            receiver,
+            last_request: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The cover found for `track`; starts a lookup the first time it is asked
+    /// and returns `None` until that is done
+    pub fn cover_url(&mut self, track: &TrackInfo) -> Option<String> {
+        if self.config.sources.is_empty() {
+            return None;
+        }
+        for (key, found) in self.receiver.try_iter() {
+            self.pending.remove(&key);
+            match found {
+                Ok(found) => {
+                    self.failed.remove(&key);
+                    self.known.insert(key, found);
+                }
+                Err(e) => {
+                    let failures = self.failed.get(&key).map_or(1, |(failures, _)| failures + 1);
+                    let delay = RETRY_DELAY.saturating_mul(1 << (failures - 1).min(16)).min(MAX_RETRY_DELAY);
+                    warn!("Cover lookup failed, trying again in {}s: {:#}", delay.as_secs(), e);
+                    self.failed.insert(key, (failures, Instant::now() + delay));
+                }
+            }
+        }
+
+        let query = Query::new(track);
+        let key = query.key()?;
+        if let Some(found) = self.known.get(&key) {
+            return found.clone();
+        }
+        if self.failed.get(&key).is_some_and(|(_, retry_at)| Instant::now() < *retry_at) {
+            return None;
+        }
+        if self.pending.insert(key.clone()) {
+            debug!("Looking up a cover for {:?}", key);
+            let config = self.config.clone();
+            let dir = self.dir.clone();
+            let last_request = self.last_request.clone();
+            let sender = self.sender.clone();
+            thread::spawn(move || {
+                let found = find(&config, &dir, &query, &last_request);
+                let _ = sender.send((key, found));
+            });
+        }
+        None
+    }
+}
+
+/// Try the sources in order. An error means a source could not be asked,
+/// so there may still be a cover, unlike `Ok(None)`.
+fn find(
+    config: &LookupConfig,
+    dir: &Path,
+    query: &Query,
+    last_request: &Mutex<Option<Instant>>,
+) -> Result<Option<String>> {
+    let mut error = None;
+    for source in &config.sources {
+        let found = match source {
+            LookupSource::Folder => Ok(folder_cover(query)),
+            LookupSource::Musicbrainz => online_cover(config, dir, query, last_request),
+        };
+        match found {
+            Ok(Some(path)) => {
+                info!("Found a cover for {:?} in {}", query.album, path.display());
+                return Ok(Some(uri::file_uri(&path)));
+            }
+            Ok(None) => {}
+            Err(e) => error = Some(e),
+        }
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok(None),
+    }
+}
+
+/// An image like cover.jpg in the folder of a local track
+fn folder_cover(query: &Query) -> Option<PathBuf> {
+    let path = PathBuf::from(crate::extract_file_path(query.url.as_deref()?)?);
+    let mut images: Vec<PathBuf> = fs::read_dir(path.parent()?)
+        .ok()?
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            let lower = |s: Option<&std::ffi::OsStr>| s.map(|s| s.to_string_lossy().to_lowercase());
+            let stem = lower(path.file_stem()).unwrap_or_default();
+            let extension = lower(path.extension()).unwrap_or_default();
+            FOLDER_NAMES.contains(&stem.as_str()) && IMAGE_EXTENSIONS.contains(&extension.as_str())
+        })
+        .collect();
+    // Prefer the names in the order of FOLDER_NAMES
+    images.sort_by_key(|path| {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_lowercase();
+        FOLDER_NAMES.iter().position(|name| *name == stem)
+    });
+    images.into_iter().next()
+}
+
+/// File name of the cached cover of an album
+fn cache_name(artist: &str, album: &str) -> String {
+    let name: String = format!("{} - {}", artist, album)
+        .to_lowercase()
+        .chars()
+        .map(|c| if c == '/' || c == '\\' || c == '%' || c.is_control() { '_' } else { c })
+        .collect();
+    // Stay well below the file name limit of 255 bytes
+    let mut end = name.len().min(200);
+    while !name.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}.cover", &name[..end])
+}
+
+/// The front cover of the album from the Cover Art Archive, found through
+/// MusicBrainz, cached in `dir`
+fn online_cover(
+    config: &LookupConfig,
+    dir: &Path,
+    query: &Query,
+    last_request: &Mutex<Option<Instant>>,
+) -> Result<Option<PathBuf>> {
+    let (Some(artist), Some(album)) = (&query.artist, &query.album) else {
+        return Ok(None);
+    };
+    let path = dir.join(cache_name(artist, album));
+    if path.exists() {
+        debug!("Using cached cover {}", path.display());
+        return Ok(Some(path));
+    }
+
+    let client = reqwest::blocking::Client::builder()
+        .user_agent(USER_AGENT)
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    // One request at a time, spaced out for MusicBrainz
+    let mut last = last_request.lock().unwrap_or_else(|e| e.into_inner());
+    let mut get = |url: String, params: &[(&str, &str)]| {
+        if let Some(elapsed) = last.map(|at| at.elapsed()) {
+            thread::sleep(REQUEST_INTERVAL.saturating_sub(elapsed));
+        }
+        *last = Some(Instant::now());
+        debug!("GET {}", url);
+        client.get(url).query(params).send()
+    };
+
+    let search = format!("releasegroup:\"{}\" AND artist:\"{}\"", escape(album), escape(artist));
+    let url = format!("{}/ws/2/release-group/", config.musicbrainz_url.trim_end_matches('/'));
+    let response = get(url, &[("query", &search), ("fmt", "json"), ("limit", "3")])?;
+    if !response.status().is_success() {
+        bail!("MusicBrainz search failed: {}", response.status());
+    }
+    let results: serde_json::Value = response.json().context("Invalid MusicBrainz response")?;
+    let ids: Vec<&str> = results["release-groups"]
+        .as_array()
+        .map(|groups| groups.iter().filter_map(|group| group["id"].as_str()).collect())
+        .unwrap_or_default();
+
+    for id in ids {
+        let url = format!("{}/release-group/{}/front-500", config.cover_art_archive_url.trim_end_matches('/'), id);
+        let response = get(url, &[])?;
+        // Most release groups have no cover
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            debug!("No cover for release group {}", id);
+            continue;
+        }
+        if !response.status().is_success() {
+            bail!("Cover Art Archive request failed: {}", response.status());
+        }
+        let bytes = response.bytes()?;
+        image::guess_format(&bytes).context("Cover Art Archive sent no image")?;
+        fs::create_dir_all(dir)?;
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, &bytes)?;
+        fs::rename(&tmp, &path)?;
+        return Ok(Some(path));
+    }
+    info!("No cover found online for {} - {}", artist, album);
+    Ok(None)
+}
+
+/// Quote a value for a MusicBrainz (Lucene) search
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+    };
+
+    /// Answers MusicBrainz and Cover Art Archive requests like the real
+    /// services would for one album, and records the paths asked for
+    struct MockServer {
+        url: String,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    impl MockServer {
+        fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let recorded = requests.clone();
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { return };
+                    serve(stream, &recorded);
+                }
+            });
+            Self { url, requests }
+        }
+
+        fn requests(&self) -> Vec<String> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    fn serve(mut stream: TcpStream, requests: &Mutex<Vec<String>>) {
+        let mut request = Vec::new();
+        let mut buf = [0; 1024];
+        while !request.ends_with(b"\r\n\r\n") {
+            match stream.read(&mut buf) {
+                Ok(0) | Err(_) => return,
+                Ok(n) => request.extend_from_slice(&buf[..n]),
+            }
+        }
+        let request = String::from_utf8_lossy(&request);
+        let path = request.split(' ').nth(1).unwrap_or_default().to_string();
+        requests.lock().unwrap().push(path.clone());
+
+        let (status, body) = if path.starts_with("/ws/2/release-group/") && path.contains("Mock+Album") {
+            let groups = r#"{"release-groups":[{"id":"no-art","score":100},{"id":"with-art","score":90}]}"#;
+            ("200 OK", groups.as_bytes().to_vec())
+        } else if path.starts_with("/ws/2/release-group/") {
+            ("200 OK", br#"{"release-groups":[]}"#.to_vec())
+        } else if path == "/release-group/with-art/front-500" {
+            ("200 OK", png())
+        } else {
+            ("404 Not Found", Vec::new())
+        };
+        let head = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, body.len());
+        let _ = stream.write_all(head.as_bytes());
+        let _ = stream.write_all(&body);
+    }
+
+    fn png() -> Vec<u8> {
+        let mut bytes = std::io::Cursor::new(Vec::new());
+        image::DynamicImage::new_rgb8(2, 2).write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+        bytes.into_inner()
+    }
+
+    fn online_config(server: &MockServer) -> LookupConfig {
+        LookupConfig {
+            sources: vec![LookupSource::Folder, LookupSource::Musicbrainz],
+            musicbrainz_url: server.url.clone(),
+            cover_art_archive_url: format!("{}/", server.url),
+        }
+    }
+
+    fn track(artist: &str, album: &str) -> TrackInfo {
+        TrackInfo {
+            title: Some("Song".to_string()),
+            artists: vec![artist.to_string()],
+            album: Some(album.to_string()),
+            ..TrackInfo::default()
+        }
+    }
+
+    #[test]
+    fn finds_covers_online_and_caches_them() {
+        let server = MockServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let config = online_config(&server);
+        let query = Query::new(&track("Mock Artist", "Mock Album"));
+        let last_request = Mutex::new(None);
+
+        let found = find(&config, dir.path(), &query, &last_request).unwrap().unwrap();
+        let path = dir.path().join("mock artist - mock album.cover");
+        assert_eq!(found, uri::file_uri(&path));
+        assert!(image::load_from_memory(&fs::read(&path).unwrap()).is_ok());
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].contains("releasegroup%3A%22Mock+Album%22+AND+artist%3A%22Mock+Artist%22"));
+        assert_eq!(requests[1..], ["/release-group/no-art/front-500", "/release-group/with-art/front-500"]);
+
+        // The second time it comes from the cache
+        assert_eq!(find(&config, dir.path(), &query, &last_request).unwrap(), Some(found));
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn unknown_albums_have_no_cover() {
+        let server = MockServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let query = Query::new(&track("Nobody", "Nothing"));
+        assert_eq!(find(&online_config(&server), dir.path(), &query, &Mutex::new(None)).unwrap(), None);
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn covers_next_to_local_files_come_first() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("Folder.JPG"), png()).unwrap();
+        fs::write(dir.path().join("cover.png"), png()).unwrap();
+        fs::write(dir.path().join("notes.txt"), "").unwrap();
+        let track = TrackInfo {
+            url: Some(uri::file_uri(&dir.path().join("song.mp3"))),
+            ..track("Mock Artist", "Mock Album")
+        };
+        let config = LookupConfig {
+            // Nothing listens here, the folder has to be enough
+            musicbrainz_url: "http://127.0.0.1:1".to_string(),
+            ..LookupConfig::default()
+        };
+        let found = find(&config, dir.path(), &Query::new(&track), &Mutex::new(None)).unwrap();
+        assert_eq!(found, Some(uri::file_uri(&dir.path().join("cover.png"))));
+    }
+
+    #[test]
+    fn lookups_run_in_the_background() {
+        let server = MockServer::start();
+        let dir = tempfile::tempdir().unwrap();
+        let mut lookup = CoverLookup::new(online_config(&server), dir.path().to_path_buf());
+        let track = track("Mock Artist", "Mock Album");
+
+        assert_eq!(lookup.cover_url(&track), None);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let found = loop {
+            if let Some(url) = lookup.cover_url(&track) {
+                break url;
+            }
+            assert!(Instant::now() < deadline, "lookup did not finish");
+            thread::sleep(Duration::from_millis(20));
+        };
+        assert!(found.ends_with(".cover"));
+        // Asking again while it ran did not start more lookups
+        assert_eq!(server.requests().len(), 3);
+    }
+
+    #[test]
+    fn failed_lookups_are_tried_again_later() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = LookupConfig {
+            sources: vec![LookupSource::Musicbrainz],
+            // Nothing listens here
+            musicbrainz_url: "http://127.0.0.1:1".to_string(),
+            ..LookupConfig::default()
+        };
+        let mut lookup = CoverLookup::new(config, dir.path().to_path_buf());
+        let track = track("Mock Artist", "Mock Album");
+        let key = Query::new(&track).key().unwrap();
+
+        lookup.cover_url(&track);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !lookup.failed.contains_key(&key) {
+            assert!(Instant::now() < deadline, "lookup did not fail");
+            thread::sleep(Duration::from_millis(20));
+            lookup.cover_url(&track);
+        }
+        // Not a miss, and not asked again right away
+        assert!(!lookup.known.contains_key(&key));
+        assert!(!lookup.pending.contains(&key));
+
+        // Once the wait is over it is
+        lookup.failed.insert(key.clone(), (1, Instant::now()));
+        lookup.cover_url(&track);
+        assert!(lookup.pending.contains(&key));
+    }
+}
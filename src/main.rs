@@ -1,89 +1,103 @@
 use anyhow::Result;
-use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, MouseButton, MouseEvent, MouseEventKind},
-    execute,
-    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
-};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::{
-    backend::{Backend, CrosstermBackend},
-    layout::{Alignment, Constraint, Direction, Layout},
+    backend::Backend,
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
-    widgets::{Block, Borders, Gauge, Paragraph},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, Gauge, Paragraph, Wrap},
     Frame, Terminal,
 };
 
-use ratatui_image::{
-    picker::Picker,
-    StatefulImage,
-    protocol::StatefulProtocol,
-};
-
-use image;
+use image::DynamicImage;
+use tokio::sync::mpsc::UnboundedReceiver;
 
 use std::{
     io::{self, BufReader},
     fs,
     time::{Duration, Instant},
     collections::HashMap,
-    fs::OpenOptions,
-    path::Path,
 };
 
 use log::{info, error};
 
+mod backend;
+mod blockart;
+mod config;
+mod cover;
+mod covercache;
+mod favorites;
+mod i18n;
+mod json_store;
+#[cfg(feature = "local-player")]
+mod local;
+mod logging;
+mod lookup;
+mod marquee;
+mod mpd;
+mod mux;
 mod music;
-use music::MusicPlayer;
-
-// Initialize logging to file
-fn init_logging() -> Result<()> {
-    let log_file = "music-tray.log";
-    
-    // Create log file if it doesn't exist
-    if !Path::new(log_file).exists() {
-        std::fs::File::create(log_file)?;
-    }
-    
-    // Configure env_logger to write to file
-    env_logger::Builder::from_default_env()
-        .target(env_logger::Target::Pipe(Box::new(
-            OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(log_file)?
-        )))
-        .init();
-    
-    info!("Music Tray application started");
-    Ok(())
-}
-
+mod policy;
+mod resume;
+mod text;
+mod tui;
+mod uri;
+use backend::{MprisBackend, MultiBackend, PlayerBackend, PlayerEntry};
+use clap::Parser;
+use config::{Cli, Command, Config};
+use cover::{CoverImage, CoverRenderer};
+use covercache::CoverCache;
+use logging::LogBuffer;
+use lookup::CoverLookup;
+use marquee::Marquees;
+use favorites::FavoritesStore;
+use i18n::{Catalog, Language};
+use music::{MusicPlayer, PlayerState, TrackInfo};
+use resume::PositionStore;
+use tui::AppSignal;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    let cli = Cli::parse();
+    let config = Config::load(cli.config.as_deref())?;
+
     // Initialize logging
-    init_logging()?;
-    
+    let logs = logging::init_logging(&config.log, cli.log_level)?;
+
+    if let Some(Command::Open { uri, player }) = &cli.command {
+        return open_uri(make_backend(&cli, &config)?, uri, player.as_deref());
+    }
+
+    // Fails on a bad --play path, so before the terminal is taken over; the
+    // D-Bus connection is made lazily by the backend
+    let mut music_player = MusicPlayer::new(make_backend(&cli, &config)?);
+
+    // Make sure the terminal is restored on panics and termination signals
+    tui::install_panic_hook();
+    let mut signals = tui::spawn_signal_listener()?;
+
     // Setup terminal
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    let mut terminal = tui::init()?;
 
     info!("Terminal setup completed");
 
     // Create app and run
-    let app = App::new();
-    let res = run_app(&mut terminal, app).await;
+    music_player.set_policy(config.policy.clone());
+    let positions = PositionStore::load(&config::state_dir().join("positions.json")).unwrap_or_else(|e| {
+        error!("Not restoring playback positions: {:#}", e);
+        PositionStore::default()
+    });
+    music_player.set_resume(config.resume.clone(), positions);
+    let favorites = FavoritesStore::load(&config::state_dir().join("favorites.json")).unwrap_or_else(|e| {
+        error!("Not loading favorites: {:#}", e);
+        FavoritesStore::default()
+    });
+    music_player.set_favorites(favorites);
+    let app = App::new(music_player, &config, logs);
+    let res = run_app(&mut terminal, app, &mut signals).await;
 
     // Restore terminal
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    tui::restore()?;
 
     if let Err(err) = res {
         error!("Application error: {err:?}");
@@ -93,24 +107,89 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
+/// What the progress display shows, switched with T or a click on it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+enum TimeDisplay {
+    /// Position and length
+    #[default]
+    Elapsed,
+    /// Time left at the current speed
+    Remaining,
+    Percentage,
+}
+
+impl TimeDisplay {
+    fn next(self) -> Self {
+        match self {
+            TimeDisplay::Elapsed => TimeDisplay::Remaining,
+            TimeDisplay::Remaining => TimeDisplay::Percentage,
+            TimeDisplay::Percentage => TimeDisplay::Elapsed,
+        }
+    }
+}
+
 struct App {
     music_player: MusicPlayer,
     should_quit: bool,
     button_positions: HashMap<String, (u16, u16, u16, u16)>, // button_name -> (x, y, width, height)
-    image: Option<StatefulProtocol>,
-    picker: Option<Picker>,
+    image: Option<CoverImage>,
+    /// The decoded cover behind `image`, to encode it again at another size
+    cover_source: Option<DynamicImage>,
+    cover: CoverRenderer,
+    cover_cache: CoverCache,
+    cover_lookup: CoverLookup,
+    /// Terminal size in cells at the last draw
+    screen: (u16, u16),
+    /// Show the cover on the whole screen
+    zoom_cover: bool,
     current_cover_url: Option<String>,
+    card_covers: HashMap<String, (String, Option<CoverImage>)>, // player id -> (cover url, image)
+    show_dashboard: bool,
+    /// First row of dashboard cards shown; follows the focused player
+    dashboard_scroll: usize,
+    show_favorites: bool,
+    /// Scrolling title, artist and album
+    marquees: Marquees,
+    /// UI strings in the user's language
+    tr: Catalog,
+    time_display: TimeDisplay,
+    logs: LogBuffer,
+    show_logs: bool,
+    show_details: bool,
+    details_scroll: u16,
+    /// Player waiting for the user to confirm quitting it
+    confirm_quit: Option<PlayerEntry>,
+    /// Text typed into the open URI prompt while it is shown
+    open_prompt: Option<String>,
 }
 
 impl App {
-    fn new() -> Self {
+    fn new(music_player: MusicPlayer, config: &Config, logs: LogBuffer) -> Self {
         Self {
-            music_player: MusicPlayer::new(),
+            music_player,
             should_quit: false,
             button_positions: HashMap::new(),
             image: None,
-            picker: None,
+            cover_source: None,
+            cover: CoverRenderer::new(config.cover.clone()),
+            cover_cache: CoverCache::new(config.cover.cache_mb * 1024 * 1024),
+            cover_lookup: CoverLookup::new(config.cover.lookup.clone(), config::cache_dir().join("covers")),
+            screen: (0, 0),
+            zoom_cover: false,
             current_cover_url: None,
+            card_covers: HashMap::new(),
+            show_dashboard: false,
+            dashboard_scroll: 0,
+            show_favorites: false,
+            marquees: Marquees::new(config.marquee.clone()),
+            tr: Catalog::new(config.language.unwrap_or_else(Language::detect)),
+            time_display: TimeDisplay::default(),
+            logs,
+            show_logs: config.log.show_pane,
+            show_details: false,
+            details_scroll: 0,
+            confirm_quit: None,
+            open_prompt: None,
         }
     }
 
@@ -120,112 +199,135 @@ impl App {
         
         // Update image if cover URL changed
         self.update_cover_image();
+        if self.show_dashboard {
+            self.update_card_covers();
+        }
     }
 
     fn update_cover_image(&mut self) {
-        let track_info = self.music_player.get_current_track();
-        
+        let track_info = self.music_player.get_current_track().clone();
+        // Tracks without a cover get the one the lookup found, once it has
+        let track_cover = track_info.cover_url.clone().or_else(|| self.cover_lookup.cover_url(&track_info));
+
         // Check if cover URL has changed
-        let cover_url_changed = self.current_cover_url.as_ref() != track_info.cover_url.as_ref();
-        
-        if let Some(cover_url) = &track_info.cover_url {
+        let cover_url_changed = self.current_cover_url != track_cover;
+
+        if let Some(cover_url) = track_cover {
             if cover_url_changed {
                 info!("Cover URL changed to: {}", cover_url);
-                
-                // Initialize picker if not already done
-                if self.picker.is_none() {
-                    match Picker::from_query_stdio() {
-                        Ok(picker) => {
-                            self.picker = Some(picker);
-                            info!("Initialized image picker");
-                        }
-                        Err(e) => {
-                            error!("Failed to initialize image picker: {}", e);
-                            return;
-                        }
-                    }
-                }
-                
+
                 // Load image if URL changed
-                if let Some(file_path) = self.extract_file_path(cover_url) {
-                    info!("Attempting to load image from: {}", file_path);
-                    
-                    if let Some(ref mut picker) = self.picker {
-                        match fs::File::open(&file_path) {
-                            Ok(file) => {
-                                let reader = image::ImageReader::new(BufReader::new(file)).with_guessed_format();
-                                match reader {
-                                    Ok(reader) => {
-                                        let format = reader.format();
-                                        info!("Detected image format: {:?} for file: {}", format, file_path);
-                                        
-                                        match reader.decode() {
-                                            Ok(dyn_img) => {
-                                                let protocol = picker.new_resize_protocol(dyn_img);
-                                                self.image = Some(protocol);
-                                                self.current_cover_url = Some(cover_url.clone());
-                                                info!("Successfully loaded cover image: {} (format: {:?})", file_path, format);
-                                            }
-                                            Err(e) => {
-                                                error!("Failed to decode image: {} (detected format: {:?}), error: {}", file_path, format, e);
-                                                self.image = None;
-                                            }
-                                        }
-                                    }
-                                    Err(e) => {
-                                        error!("Failed to create image reader: {} {}", file_path, e);
-                                        self.image = None;
-                                    }
-                                }
-                            }
-                            Err(e) => {
-                                error!("Failed to open image file: {} {}", file_path, e);
-                                self.image = None;
-                            }
+                if extract_file_path(&cover_url).is_some() {
+                    // Big enough for the full-screen cover too
+                    let size = self.pixels(self.screen);
+                    match self.cover_cache.get(&cover_url, size, || load_cover(&cover_url)) {
+                        Some(dyn_img) => {
+                            self.cover_source = Some(dyn_img.clone());
+                            self.image = Some(self.cover.prepare(dyn_img));
+                            self.current_cover_url = Some(cover_url);
+                        }
+                        None => {
+                            self.image = None;
+                            self.cover_source = None;
                         }
-                    } else {
-                        error!("Failed to initialize image picker");
-                        self.image = None;
-                        self.current_cover_url = None;
                     }
-
                 } else {
                     info!("Cover URL is not a file:// URL, skipping image loading");
                     self.image = None;
+                    self.cover_source = None;
                     self.current_cover_url = None;
                 }
             }
-        } else {
-            if self.current_cover_url.is_some() {
-                info!("No cover URL available, clearing image");
-                self.image = None;
-                self.current_cover_url = None;
-            }
+        } else if self.current_cover_url.is_some() {
+            info!("No cover URL available, clearing image");
+            self.image = None;
+            self.cover_source = None;
+            self.current_cover_url = None;
         }
     }
 
-    fn extract_file_path(&self, url: &str) -> Option<String> {
-        if url.starts_with("file://") {
-            let path = url.trim_start_matches("file://");
-            // URL decode the path to handle Chinese characters and other encoded characters
-            match urlencoding::decode(path) {
-                Ok(decoded_path) => Some(decoded_path.to_string()),
-                Err(_) => {
-                    // If URL decoding fails, try basic replacements for common cases
-                    let basic_decoded = path
-                        .replace("%20", " ")
-                        .replace("%2F", "/")
-                        .replace("%5C", "\\")
-                        .replace("%3A", ":");
-                    Some(basic_decoded)
-                }
-            }
-        } else {
-            None
+    /// Pixels covered by `cells` terminal cells
+    fn pixels(&mut self, (columns, rows): (u16, u16)) -> (u32, u32) {
+        let (width, height) = self.cover.cell_size();
+        (columns as u32 * width as u32, rows as u32 * height as u32)
+    }
+
+    /// Switch between the full-screen cover and the normal view. The cover
+    /// is encoded again for the new size instead of stretching the old one.
+    fn toggle_cover_zoom(&mut self) {
+        self.zoom_cover = !self.zoom_cover;
+        if let Some(source) = self.cover_source.clone() {
+            self.image = Some(self.cover.prepare(source));
+        }
+    }
+
+    /// Load the small covers shown on the dashboard cards
+    fn update_card_covers(&mut self) {
+        let players = self.music_player.get_players();
+        self.card_covers.retain(|id, _| players.iter().any(|p| &p.entry.id == id));
+
+        let changed: Vec<(String, String)> = players
+            .iter()
+            .filter_map(|p| {
+                let url = p.track.cover_url.clone().or_else(|| self.cover_lookup.cover_url(&p.track))?;
+                Some((p.entry.id.clone(), url))
+            })
+            .filter(|(id, url)| self.card_covers.get(id).is_none_or(|(loaded, _)| loaded != url))
+            .collect();
+        for (id, url) in changed {
+            // Failures are remembered too, so a broken cover is not retried every tick
+            let size = self.pixels(CARD_COVER_SIZE);
+            let image = self.cover_cache.get(&url, size, || load_cover(&url)).map(|img| self.cover.prepare(img));
+            self.card_covers.insert(id, (url, image));
         }
     }
 
+    /// Detect the cover protocol again and reload every cover with it
+    fn retry_cover_protocol(&mut self) {
+        self.cover.retry();
+        self.reload_covers();
+    }
+
+    /// Encode every cover again, e.g. after a multiplexer lost the images
+    fn reload_covers(&mut self) {
+        self.image = None;
+        self.cover_source = None;
+        self.current_cover_url = None;
+        self.card_covers.clear();
+    }
+
     fn on_key(&mut self, key: KeyCode) {
+        if let Some(input) = self.open_prompt.as_mut() {
+            match key {
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Tab => {
+                    if let Some(completed) = uri::complete_path(input) {
+                        *input = completed;
+                    }
+                }
+                // On errors the prompt stays open so a typo can be fixed
+                KeyCode::Enter if self.music_player.open_uri(input).is_ok() => {
+                    self.open_prompt = None;
+                }
+                KeyCode::Esc => self.open_prompt = None,
+                _ => {}
+            }
+            return;
+        }
+        if let Some(player) = self.confirm_quit.take() {
+            if key == KeyCode::Char('y') {
+                self.music_player.quit_player(&player.id);
+            }
+            return;
+        }
+        if self.zoom_cover && matches!(key, KeyCode::Esc | KeyCode::Char('z')) {
+            self.toggle_cover_zoom();
+            return;
+        }
+
         match key {
             KeyCode::Char('q') => {
                 self.should_quit = true;
@@ -236,50 +338,300 @@ impl App {
             KeyCode::Char('n') => {
                 self.music_player.next();
             }
+            KeyCode::Char('s') => {
+                self.music_player.stop();
+            }
+            KeyCode::Char('w') => {
+                self.music_player.raise();
+            }
+            KeyCode::Char('u') => {
+                self.open_prompt = Some(String::new());
+            }
+            KeyCode::Char('Q') => {
+                self.ask_quit_player();
+            }
             KeyCode::Char('p') => {
                 self.music_player.previous();
             }
+            KeyCode::Char('l') => {
+                self.show_logs = !self.show_logs;
+            }
+            KeyCode::Char('d') => {
+                self.show_dashboard = !self.show_dashboard;
+                self.show_favorites = false;
+            }
+            KeyCode::Char('f') => {
+                self.music_player.toggle_like();
+            }
+            KeyCode::Char('F') => {
+                self.show_favorites = !self.show_favorites;
+                self.show_dashboard = false;
+            }
+            KeyCode::Char('o') => {
+                self.music_player.pause_all_others();
+            }
+            KeyCode::Char('x') => {
+                self.music_player.toggle_exclusive();
+            }
+            KeyCode::Char('r') => {
+                self.music_player.accept_resume();
+            }
+            KeyCode::Esc => {
+                self.music_player.dismiss_resume();
+            }
+            KeyCode::Char(']') => {
+                self.music_player.step_rate(true);
+            }
+            KeyCode::Char('[') => {
+                self.music_player.step_rate(false);
+            }
+            KeyCode::Char('=') => {
+                self.music_player.reset_rate();
+            }
+            KeyCode::Char('+') => {
+                self.music_player.step_volume(0.05);
+            }
+            KeyCode::Char('-') => {
+                self.music_player.step_volume(-0.05);
+            }
+            KeyCode::Tab => {
+                self.music_player.select_next_player();
+            }
+            KeyCode::Char('c') => {
+                self.retry_cover_protocol();
+            }
+            KeyCode::Char('z') => {
+                self.toggle_cover_zoom();
+            }
+            KeyCode::Char('t') => {
+                self.time_display = self.time_display.next();
+            }
+            KeyCode::Char('i') => {
+                self.show_details = !self.show_details;
+                self.details_scroll = 0;
+            }
+            KeyCode::Up if self.show_details => {
+                self.details_scroll = self.details_scroll.saturating_sub(1);
+            }
+            KeyCode::Down if self.show_details => {
+                self.details_scroll = self.details_scroll.saturating_add(1);
+            }
             _ => {}
         }
     }
 
+    /// Ask before closing the current player; players that cannot quit
+    /// get the rejection message right away
+    fn ask_quit_player(&mut self) {
+        let Some(player) = self.music_player.get_current_player().cloned() else {
+            return;
+        };
+        if self.music_player.get_current_capabilities().can_quit {
+            self.confirm_quit = Some(player);
+        } else {
+            self.music_player.quit_player(&player.id);
+        }
+    }
+
     fn on_mouse(&mut self, mouse: MouseEvent) {
-        if let MouseEventKind::Down(MouseButton::Left) = mouse.kind {
-            self.handle_button_click(mouse.column, mouse.row);
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => self.handle_button_click(mouse.column, mouse.row),
+            MouseEventKind::Moved => {
+                // Scrolling text holds still under the mouse
+                let hovered = self.button_at(mouse.column, mouse.row).and_then(|name| name.strip_prefix("marquee:"));
+                let hovered = hovered.map(str::to_string);
+                self.marquees.set_hovered(hovered.as_deref());
+            }
+            _ => {}
         }
     }
 
+    /// Name of the button at (x, y); nested areas (a button on a dashboard
+    /// card) resolve to the innermost one
+    fn button_at(&self, x: u16, y: u16) -> Option<&str> {
+        self
+            .button_positions
+            .iter()
+            .filter(|(_, (btn_x, btn_y, btn_width, btn_height))| {
+                x >= *btn_x && x < *btn_x + *btn_width &&
+                y >= *btn_y && y < *btn_y + *btn_height
+            })
+            .min_by_key(|(_, (_, _, btn_width, btn_height))| *btn_width as u32 * *btn_height as u32)
+            .map(|(button_name, _)| button_name.as_str())
+    }
+
     fn handle_button_click(&mut self, x: u16, y: u16) {
-        // Check if click is within any button area
-        for (button_name, (btn_x, btn_y, btn_width, btn_height)) in &self.button_positions {
-            if x >= *btn_x && x < *btn_x + *btn_width && 
-               y >= *btn_y && y < *btn_y + *btn_height {
-                match button_name.as_str() {
-                    "previous" => {
-                        self.music_player.previous();
-                        info!("Previous button clicked");
-                    }
-                    "play_pause" => {
-                        self.music_player.toggle_play_pause();
-                        info!("Play/Pause button clicked");
-                    }
-                    "next" => {
-                        self.music_player.next();
-                        info!("Next button clicked");
+        let clicked = self.button_at(x, y).map(str::to_string);
+
+        // A click outside the confirmation prompt dismisses it
+        if self.confirm_quit.is_some()
+            && !matches!(clicked.as_deref(), Some("confirm_quit") | Some("cancel_quit"))
+        {
+            self.confirm_quit = None;
+            return;
+        }
+
+        if let Some(button_name) = clicked {
+            match button_name.as_str() {
+                "previous" => {
+                    self.music_player.previous();
+                    info!("Previous button clicked");
+                }
+                "play_pause" => {
+                    self.music_player.toggle_play_pause();
+                    info!("Play/Pause button clicked");
+                }
+                "next" => {
+                    self.music_player.next();
+                    info!("Next button clicked");
+                }
+                "stop" => {
+                    self.music_player.stop();
+                    info!("Stop button clicked");
+                }
+                "confirm_quit" => {
+                    if let Some(player) = self.confirm_quit.take() {
+                        self.music_player.quit_player(&player.id);
                     }
-                    "quit" => {
-                        self.should_quit = true;
-                        info!("Quit button clicked");
+                }
+                "cancel_quit" => {
+                    self.confirm_quit = None;
+                }
+                "quit" => {
+                    self.should_quit = true;
+                    info!("Quit button clicked");
+                }
+                "like" => {
+                    self.music_player.toggle_like();
+                }
+                "cover" | "cover_zoom" => {
+                    self.toggle_cover_zoom();
+                }
+                name if name.starts_with("marquee:") => {
+                    self.marquees.toggle(&name["marquee:".len()..]);
+                }
+                "progress" => {
+                    self.time_display = self.time_display.next();
+                }
+                "track" => {
+                    self.show_details = !self.show_details;
+                    self.details_scroll = 0;
+                }
+                "pause_others" => {
+                    self.music_player.pause_all_others();
+                    info!("Pause all others button clicked");
+                }
+                // Focusing the next player scrolls to it
+                "dashboard_more" => {
+                    self.music_player.select_next_player();
+                }
+                name => {
+                    if let Some(id) = name.strip_prefix("card_play:") {
+                        self.music_player.toggle_play_pause_for(id);
+                    } else if let Some(id) = name.strip_prefix("card:") {
+                        self.music_player.select_player(id);
                     }
-                    _ => {}
                 }
-                break;
             }
         }
     }
 }
 
-async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+/// MPRIS players, next to MPD and the built-in player when they are configured
+fn make_backend(cli: &Cli, config: &Config) -> Result<Box<dyn PlayerBackend>> {
+    let mut backends: Vec<Box<dyn PlayerBackend>> = vec![Box::new(MprisBackend::new())];
+    if config.mpd.enabled {
+        backends.push(Box::new(mpd::MpdBackend::new(config.mpd.clone())));
+    }
+    if let Some(path) = cli.play.as_deref().or(config.local.path.as_deref()) {
+        backends.push(local_backend(config, path)?);
+    }
+    Ok(Box::new(MultiBackend::new(backends)))
+}
+
+#[cfg(feature = "local-player")]
+fn local_backend(config: &Config, path: &std::path::Path) -> Result<Box<dyn PlayerBackend>> {
+    let path = uri::expand_home(&path.to_string_lossy());
+    Ok(Box::new(local::LocalBackend::from_config(&config.local, &path)?))
+}
+
+#[cfg(not(feature = "local-player"))]
+fn local_backend(_config: &Config, path: &std::path::Path) -> Result<Box<dyn PlayerBackend>> {
+    anyhow::bail!("Cannot play {}: music-tray was built without the local-player feature", path.display())
+}
+
+/// `music-tray open`: send `uri` to a player of `backend` and exit
+fn open_uri(backend: Box<dyn PlayerBackend>, uri: &str, player: Option<&str>) -> Result<()> {
+    let mut music_player = MusicPlayer::new(backend);
+    music_player.update();
+    if let Some(reason) = music_player.bus_error() {
+        anyhow::bail!("D-Bus session bus unavailable: {}", reason);
+    }
+    match player {
+        Some(name) if !music_player.select_player_by_name(name) => anyhow::bail!("No player matching {}", name),
+        _ => {}
+    }
+    let Some(target) = music_player.get_current_player().map(|p| p.identity.clone()) else {
+        anyhow::bail!("No player found");
+    };
+    music_player.open_uri(uri)?;
+    println!("Opened {} in {}", uri, target);
+    Ok(())
+}
+
+fn extract_file_path(url: &str) -> Option<String> {
+    if url.starts_with("file://") {
+        let path = url.trim_start_matches("file://");
+        // URL decode the path to handle Chinese characters and other encoded characters
+        match urlencoding::decode(path) {
+            Ok(decoded_path) => Some(decoded_path.to_string()),
+            Err(_) => {
+                // If URL decoding fails, try basic replacements for common cases
+                let basic_decoded = path
+                    .replace("%20", " ")
+                    .replace("%2F", "/")
+                    .replace("%5C", "\\")
+                    .replace("%3A", ":");
+                Some(basic_decoded)
+            }
+        }
+    } else {
+        None
+    }
+}
+
+/// Open and decode the image behind a file:// cover URL
+fn load_cover(cover_url: &str) -> Option<DynamicImage> {
+    let file_path = extract_file_path(cover_url)?;
+    info!("Attempting to load image from: {}", file_path);
+
+    let file = fs::File::open(&file_path)
+        .map_err(|e| error!("Failed to open image file: {} {}", file_path, e))
+        .ok()?;
+    let reader = image::ImageReader::new(BufReader::new(file))
+        .with_guessed_format()
+        .map_err(|e| error!("Failed to create image reader: {} {}", file_path, e))
+        .ok()?;
+    let format = reader.format();
+    info!("Detected image format: {:?} for file: {}", format, file_path);
+
+    match reader.decode() {
+        Ok(dyn_img) => {
+            info!("Successfully loaded cover image: {} (format: {:?})", file_path, format);
+            Some(dyn_img)
+        }
+        Err(e) => {
+            error!("Failed to decode image: {} (detected format: {:?}), error: {}", file_path, format, e);
+            None
+        }
+    }
+}
+
+async fn run_app<B: Backend>(
+    terminal: &mut Terminal<B>,
+    mut app: App,
+    signals: &mut UnboundedReceiver<AppSignal>,
+) -> io::Result<()> {
     let mut last_tick = Instant::now();
     let tick_rate = Duration::from_millis(250);
 
@@ -292,24 +644,52 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Re
 
         if crossterm::event::poll(timeout)? {
             match event::read()? {
-                Event::Key(key) => {
-                    if key.kind == KeyEventKind::Press {
-                        app.on_key(key.code);
-                    }
+                // Raw mode turns Ctrl-Z into a key press instead of SIGTSTP
+                Event::Key(key)
+                    if key.kind == KeyEventKind::Press
+                        && key.code == KeyCode::Char('z')
+                        && key.modifiers.contains(KeyModifiers::CONTROL) =>
+                {
+                    tui::suspend()?;
+                    terminal.clear()?;
+                }
+                Event::Key(key) if key.kind == KeyEventKind::Press => {
+                    app.on_key(key.code);
                 }
                 Event::Mouse(mouse) => {
                     app.on_mouse(mouse);
                 }
+                // tmux and screen do not clear images when panes are resized or switched
+                Event::Resize(_, _) | Event::FocusGained if app.cover.in_multiplexer() => {
+                    terminal.clear()?;
+                    app.reload_covers();
+                }
                 _ => {}
             }
         }
 
+        while let Ok(signal) = signals.try_recv() {
+            match signal {
+                AppSignal::Terminate => app.should_quit = true,
+                AppSignal::Suspend => {
+                    tui::suspend()?;
+                    terminal.clear()?;
+                }
+                AppSignal::Resume => {
+                    tui::resume()?;
+                    terminal.clear()?;
+                }
+            }
+        }
+
         if last_tick.elapsed() >= tick_rate {
+            app.marquees.tick(last_tick.elapsed());
             app.on_tick();
             last_tick = Instant::now();
         }
 
         if app.should_quit {
+            app.music_player.save_positions();
             break;
         }
     }
@@ -318,67 +698,316 @@ async fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Re
 }
 
 fn ui(f: &mut Frame, app: &mut App) {
+    // Buttons register themselves while drawing, drop the ones from the last view
+    app.button_positions.clear();
+    app.screen = (f.area().width, f.area().height);
+
+    if app.zoom_cover {
+        render_cover_zoom(f, app);
+    } else {
+        render_main(f, app);
+    }
+
+    if let Some(multiplexer) = app.cover.passthrough() {
+        mux::passthrough(f.buffer_mut(), multiplexer);
+    }
+}
+
+/// Title, player view, log pane and controls
+fn render_main(f: &mut Frame, app: &mut App) {
     let chunks = Layout::default()
         .direction(Direction::Vertical)
         .margin(1)
         .constraints([
             Constraint::Length(3), // Title
             Constraint::Min(10),   // Main content
+            Constraint::Length(if app.show_logs { 8 } else { 0 }), // Log pane
             Constraint::Length(5), // Controls
         ])
         .split(f.area());
 
     // Title
-    let title = Paragraph::new("♪ Music Tray")
+    let title_text = app.tr.get(if app.music_player.is_exclusive() { "app.exclusive" } else { "app.title" });
+    let mut title_block = Block::default().borders(Borders::ALL);
+    if let Some(message) = app.music_player.status_message() {
+        let message = app.tr.message(message);
+        title_block = title_block.title_bottom(
+            Line::styled(
+                format!(" {} ", text::truncate(&message, chunks[0].width.saturating_sub(4) as usize)),
+                Style::default().fg(Color::Red),
+            )
+            .centered(),
+        );
+    }
+    let title = Paragraph::new(title_text)
         .style(Style::default().fg(Color::Yellow))
         .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL));
+        .block(title_block);
     f.render_widget(title, chunks[0]);
 
     // Main content area
+    if app.show_favorites {
+        render_favorites(f, app, chunks[1]);
+    } else if app.show_dashboard {
+        render_dashboard(f, app, chunks[1]);
+    } else {
+        render_now_playing(f, app, chunks[1]);
+    }
+
+    // Recent log lines
+    if app.show_logs {
+        let log_block = Block::default().borders(Borders::ALL).title(app.tr.get("log.title"));
+        let visible = log_block.inner(chunks[2]).height as usize;
+        let log_width = log_block.inner(chunks[2]).width as usize;
+        let lines: Vec<Line> = app
+            .logs
+            .last(visible)
+            .into_iter()
+            .map(|line| {
+                let color = if line.contains(" ERROR ") {
+                    Color::Red
+                } else if line.contains(" WARN ") {
+                    Color::Yellow
+                } else {
+                    Color::Gray
+                };
+                Line::styled(text::truncate(&line, log_width), Style::default().fg(color))
+            })
+            .collect();
+        f.render_widget(Paragraph::new(lines).block(log_block), chunks[2]);
+    }
+
+    // Clickable Controls
+    let control_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(20), // Previous button
+            Constraint::Percentage(20), // Play/Pause button
+            Constraint::Percentage(20), // Stop button
+            Constraint::Percentage(20), // Next button
+            Constraint::Percentage(20), // Quit button
+        ])
+        .split(chunks[3]);
+
+    // Store button positions for mouse click detection
+    app.button_positions.insert("previous".to_string(), 
+        (control_chunks[0].x, control_chunks[0].y, control_chunks[0].width, control_chunks[0].height));
+    app.button_positions.insert("play_pause".to_string(), 
+        (control_chunks[1].x, control_chunks[1].y, control_chunks[1].width, control_chunks[1].height));
+    app.button_positions.insert("stop".to_string(),
+        (control_chunks[2].x, control_chunks[2].y, control_chunks[2].width, control_chunks[2].height));
+    app.button_positions.insert("next".to_string(), 
+        (control_chunks[3].x, control_chunks[3].y, control_chunks[3].width, control_chunks[3].height));
+    app.button_positions.insert("quit".to_string(), 
+        (control_chunks[4].x, control_chunks[4].y, control_chunks[4].width, control_chunks[4].height));
+
+    // Buttons the current player does not support are greyed out
+    let capabilities = app.music_player.get_current_capabilities();
+
+    // Previous button
+    let prev_button = Paragraph::new(app.tr.get("button.previous"))
+        .style(button_style(Color::Blue, capabilities.can_control && capabilities.can_go_previous))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(app.tr.get("key.previous")));
+    f.render_widget(prev_button, control_chunks[0]);
+
+    // Play/Pause button
+    let track_info = app.music_player.get_current_track();
+    let play_pause_text = app.tr.get(if track_info.is_playing { "button.pause" } else { "button.play" });
+    let play_pause_button = Paragraph::new(play_pause_text)
+        .style(button_style(Color::Red, capabilities.can_control && capabilities.can_pause))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(app.tr.get("key.play_pause")));
+    f.render_widget(play_pause_button, control_chunks[1]);
+
+    // Stop button
+    let stop_button = Paragraph::new(app.tr.get("button.stop"))
+        .style(button_style(Color::Magenta, capabilities.can_control))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(app.tr.get("key.stop")));
+    f.render_widget(stop_button, control_chunks[2]);
+
+    // Next button
+    let next_button = Paragraph::new(app.tr.get("button.next"))
+        .style(button_style(Color::Green, capabilities.can_control && capabilities.can_go_next))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(app.tr.get("key.next")));
+    f.render_widget(next_button, control_chunks[3]);
+
+    // Quit button
+    let quit_button = Paragraph::new(app.tr.get("button.quit"))
+        .style(Style::default().fg(Color::White))
+        .alignment(Alignment::Center)
+        .block(Block::default().borders(Borders::ALL).title(app.tr.get("key.quit")));
+    f.render_widget(quit_button, control_chunks[4]);
+
+    if let Some(player) = app.confirm_quit.clone() {
+        render_quit_prompt(f, app, &player);
+    }
+    if let Some(input) = &app.open_prompt {
+        render_open_prompt(f, input, app.tr);
+    }
+}
+
+/// The cover on the whole screen, with the track in the border
+fn render_cover_zoom(f: &mut Frame, app: &mut App) {
+    let area = f.area();
+    app.button_positions.insert("cover_zoom".to_string(), (area.x, area.y, area.width, area.height));
+
+    let track_info = app.music_player.get_current_track();
+    let mut heading = format!("♪ {}", track_info.title.as_deref().unwrap_or(app.tr.get("unknown.title")));
+    if let Some(artist) = track_info.artist_display() {
+        heading.push_str(&format!(" - {}", artist));
+    }
+    let heading = format!(" {} ", text::truncate(&heading, area.width.saturating_sub(4) as usize));
+    let zoom_block = Block::default()
+        .borders(Borders::ALL)
+        .title(Line::styled(heading, Style::default().fg(Color::White).add_modifier(Modifier::BOLD)).centered())
+        .title_bottom(
+            Line::styled(format!(" {} ", app.tr.get("zoom.close")), Style::default().fg(Color::DarkGray)).centered(),
+        );
+    let inner_area = zoom_block.inner(area);
+    f.render_widget(zoom_block, area);
+
+    match app.image.as_mut() {
+        Some(image) => image.render_zoomed(f, inner_area),
+        None => {
+            let placeholder = Paragraph::new(format!("♪\n\n{}\n\n♪", app.tr.get("cover.none")))
+                .style(Style::default().fg(Color::Blue))
+                .alignment(Alignment::Center);
+            f.render_widget(placeholder, centered(inner_area, inner_area.width, 6));
+        }
+    }
+}
+
+/// `width` x `height` area in the middle of the screen
+fn centered(screen: Rect, width: u16, height: u16) -> Rect {
+    let width = screen.width.min(width);
+    let height = screen.height.min(height);
+    Rect::new(
+        screen.x + (screen.width - width) / 2,
+        screen.y + (screen.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Prompt for a URL or local path to open in the current player
+fn render_open_prompt(f: &mut Frame, input: &str, tr: Catalog) {
+    let area = centered(f.area(), 70, 4);
+    f.render_widget(Clear, area);
+
+    let prompt_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Yellow))
+        .title(tr.get("open.title"));
+    let inner_area = prompt_block.inner(area);
+    f.render_widget(prompt_block, area);
+
+    // Keep the end of long input visible
+    // Keep one column for the cursor
+    let shown = text::tail(input, inner_area.width.saturating_sub(3) as usize);
+    f.render_widget(Paragraph::new(format!("> {}", shown)), inner_area);
+    f.set_cursor_position((inner_area.x + 2 + text::width(shown) as u16, inner_area.y));
+}
+
+/// Centered "quit the player?" prompt on top of everything else
+fn render_quit_prompt(f: &mut Frame, app: &mut App, player: &PlayerEntry) {
+    let area = centered(f.area(), 44, 6);
+    f.render_widget(Clear, area);
+
+    let prompt_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::Red))
+        .title(app.tr.get("quit.title"));
+    let inner_area = prompt_block.inner(area);
+    f.render_widget(prompt_block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(2), // Question
+            Constraint::Length(1), // Buttons
+        ])
+        .split(inner_area);
+    let question = Paragraph::new(app.tr.format("quit.question", &[("player", &player.identity)]))
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true });
+    f.render_widget(question, rows[0]);
+
+    let buttons = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(rows[1]);
+    app.button_positions.insert("confirm_quit".to_string(),
+        (buttons[0].x, buttons[0].y, buttons[0].width, buttons[0].height));
+    app.button_positions.insert("cancel_quit".to_string(),
+        (buttons[1].x, buttons[1].y, buttons[1].width, buttons[1].height));
+    f.render_widget(
+        Paragraph::new(app.tr.get("quit.yes")).style(Style::default().fg(Color::Red)).alignment(Alignment::Center),
+        buttons[0],
+    );
+    f.render_widget(Paragraph::new(app.tr.get("quit.no")).alignment(Alignment::Center), buttons[1]);
+}
+
+fn button_style(color: Color, enabled: bool) -> Style {
+    if enabled {
+        Style::default().fg(color)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    }
+}
+
+
+/// Cover, track details and progress of the current player
+fn render_now_playing(f: &mut Frame, app: &mut App, area: Rect) {
     let main_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
-        .split(chunks[1]);
+        .split(area);
 
     // Cover art area (left side)
-    let cover_title = "Cover Art";
+    let tr = app.tr;
+    let cover_title = tr.get("cover.title");
     
     // Create the cover block with title and borders
     let cover_block = Block::default()
         .borders(Borders::ALL)
         .title(cover_title)
-        .title_style(Style::default().fg(Color::Yellow));
+        .title_style(Style::default().fg(Color::Yellow))
+        .title_bottom(Line::styled(
+            format!(" {} ", tr.format("cover.status", &[("status", &tr.message(&app.cover.status()))])),
+            Style::default().fg(Color::DarkGray),
+        ));
     
     // Get the inner area for content (excluding borders and title)
     let inner_area = cover_block.inner(main_chunks[0]);
     
-    if let Some(ref mut protocol) = app.image {
+    if let Some(ref mut image) = app.image {
         // Render the loaded image in the inner area
-        let image_widget = StatefulImage::new();
-        f.render_stateful_widget(image_widget, inner_area, protocol);
+        image.render(f, inner_area);
     } else {
         // Show placeholder when no image is available
         let track_info = app.music_player.get_current_track();
-        let placeholder_text = if track_info.cover_url.is_some() {
-            if app.picker.is_some() {
+        let placeholder_text = if let Some(ref cover_url) = track_info.cover_url {
+            if app.cover.protocol().is_some() {
                 if let Some(ref current_url) = app.current_cover_url {
-                    if current_url == track_info.cover_url.as_ref().unwrap() {
-                        "♪\n\nFailed to Load\nCover Image\n\n♪"
+                    if current_url == cover_url {
+                        "cover.failed"
                     } else {
-                        "♪\n\nLoading Cover...\n\n♪"
+                        "cover.loading"
                     }
                 } else {
-                    "♪\n\nLoading Cover...\n\n♪"
+                    "cover.loading"
                 }
             } else {
-                "♪\n\nInitializing\nImage Picker...\n\n♪"
+                "cover.detecting"
             }
         } else {
-            "♪\n\nNo Cover\nAvailable\n\n♪"
+            "cover.none"
         };
         
-        let placeholder_paragraph = Paragraph::new(placeholder_text)
+        let placeholder_paragraph = Paragraph::new(format!("♪\n\n{}\n\n♪", tr.get(placeholder_text)))
             .style(Style::default().fg(Color::Blue))
             .alignment(Alignment::Center);
         f.render_widget(placeholder_paragraph, inner_area);
@@ -386,6 +1015,8 @@ fn ui(f: &mut Frame, app: &mut App) {
     
     // Render the block with borders and title
     f.render_widget(cover_block, main_chunks[0]);
+    app.button_positions.insert("cover".to_string(),
+        (main_chunks[0].x, main_chunks[0].y, main_chunks[0].width, main_chunks[0].height));
 
 
     // Track info area (right side)
@@ -393,128 +1024,906 @@ fn ui(f: &mut Frame, app: &mut App) {
     let track_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([
-            Constraint::Length(2), // Connection status
+            Constraint::Length(4), // Connection status
             Constraint::Length(3), // Track name
-            Constraint::Length(3), // Artist
-            Constraint::Length(3), // Album
+            Constraint::Length(if app.show_details { 0 } else { 3 }), // Artist
+            Constraint::Length(if app.show_details { 0 } else { 3 }), // Album
             Constraint::Length(3), // Progress
-            Constraint::Min(0),    // Spacer
+            Constraint::Min(0),    // Details or spacer
         ])
         .split(main_chunks[1]);
 
     // Connection status and player info
-    let connection_status = if app.music_player.is_connected() {
-        if let Some(player) = app.music_player.get_current_player() {
-            let player_name = player.identity();
-            let cover_info = if let Some(cover_url) = &track_info.cover_url {
-                if cover_url.starts_with("file://") {
-                    format!("[+] Connected to: {}\n[F] Cover: {}", player_name, cover_url)
-                } else {
-                    format!("[+] Connected to: {}\n[W] Cover: {}", player_name, cover_url)
-                }
-            } else {
-                format!("[+] Connected to: {}", player_name)
-            };
-            cover_info
+    let connection_status = if let Some(reason) = app.music_player.bus_error() {
+        tr.format("status.no_bus", &[("reason", reason)])
+    } else if let Some(offer) = app.music_player.resume_offer() {
+        let position = format_time(offer.position, false);
+        format!(
+            "{}\n{}",
+            tr.format("status.connected", &[("player", &offer.player_name)]),
+            tr.format(
+                "status.resume",
+                &[("title", offer.title.as_deref().unwrap_or(tr.get("unknown.title"))), ("position", &position)]
+            )
+        )
+    } else if let Some(player) = app.music_player.get_current_player() {
+        let player_name = match track_info.volume {
+            Some(volume) => tr.format(
+                "status.volume",
+                &[("player", &player.identity), ("volume", &format!("{:.0}", volume * 100.0))],
+            ),
+            None => player.identity.clone(),
+        };
+        let connected = tr.format("status.connected", &[("player", &player_name)]);
+        if let Some(cover_url) = &track_info.cover_url {
+            let cover = if cover_url.starts_with("file://") { "status.local_cover" } else { "status.web_cover" };
+            format!("{}\n{}", connected, tr.format(cover, &[("url", cover_url)]))
         } else {
-            "[+] Connected (No active player)".to_string()
+            connected
         }
     } else {
-        "[-] Not connected to D-Bus".to_string()
+        tr.get("status.no_player").to_string()
     };
-    
+    let status_color = if app.music_player.bus_error().is_some() { Color::Red } else { Color::Green };
+
+    let connection_status = text::truncate_lines(&connection_status, track_chunks[0].width.saturating_sub(2) as usize);
     let status_block = Paragraph::new(connection_status)
-        .style(Style::default().fg(Color::Green))
-        .block(Block::default().borders(Borders::ALL).title("Status"));
+        .style(Style::default().fg(status_color))
+        .block(Block::default().borders(Borders::ALL).title(tr.get("status.title")));
     f.render_widget(status_block, track_chunks[0]);
 
-    // Track name with play status
+    // Track name with play status, and the like control on the right of the border
     let play_status = if track_info.is_playing { ">" } else { "||" };
-    let track_name = Paragraph::new(format!("{} {}", play_status, track_info.title.as_deref().unwrap_or("Unknown")))
+    let like = like_label(track_info, app.music_player.is_liked(), tr);
+    let like_width = (like.width() as u16).min(track_chunks[1].width.saturating_sub(2));
+    let title = app.marquees.view(
+        "title",
+        track_info.title.as_deref().unwrap_or(tr.get("unknown.title")),
+        track_chunks[1].width.saturating_sub(3 + play_status.len() as u16),
+    );
+    let track_name = Paragraph::new(format!("{} {}", play_status, title))
         .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD))
-        .block(Block::default().borders(Borders::ALL).title("Track"));
+        .block(Block::default().borders(Borders::ALL).title(tr.get("track.title")).title(like.right_aligned()));
     f.render_widget(track_name, track_chunks[1]);
+    app.button_positions.insert("track".to_string(),
+        (track_chunks[1].x, track_chunks[1].y, track_chunks[1].width, track_chunks[1].height));
+    app.button_positions.insert("like".to_string(),
+        (track_chunks[1].right().saturating_sub(like_width + 1), track_chunks[1].y, like_width, 1));
 
     // Artist
-    let artist = Paragraph::new(track_info.artist.as_deref().unwrap_or("Unknown Artist"))
+    let artist_text = track_info.artist_display();
+    let artist_text = app.marquees.view(
+        "artist",
+        artist_text.as_deref().unwrap_or(tr.get("unknown.artist")),
+        track_chunks[2].width.saturating_sub(2),
+    );
+    let artist = Paragraph::new(artist_text)
         .style(Style::default().fg(Color::Cyan))
-        .block(Block::default().borders(Borders::ALL).title("Artist"));
+        .block(Block::default().borders(Borders::ALL).title(tr.get("artist.title")));
     f.render_widget(artist, track_chunks[2]);
 
     // Album
-    let album = Paragraph::new(track_info.album.as_deref().unwrap_or("Unknown Album"))
+    let album_text = app.marquees.view(
+        "album",
+        track_info.album.as_deref().unwrap_or(tr.get("unknown.album")),
+        track_chunks[3].width.saturating_sub(2),
+    );
+    let album = Paragraph::new(album_text)
         .style(Style::default().fg(Color::Magenta))
-        .block(Block::default().borders(Borders::ALL).title("Album"));
+        .block(Block::default().borders(Borders::ALL).title(tr.get("album.title")));
     f.render_widget(album, track_chunks[3]);
 
+    // Clicking scrolling text stops it
+    for (name, area) in [("title", track_chunks[1]), ("artist", track_chunks[2]), ("album", track_chunks[3])] {
+        if area.height > 2 && app.marquees.scrolls(name) {
+            app.button_positions.insert(format!("marquee:{}", name),
+                (area.x + 1, area.y + 1, area.width.saturating_sub(2), 1));
+        }
+    }
+
     // Progress bar with time display
-    let progress = if track_info.duration > 0 {
-        (track_info.position as f64 / track_info.duration as f64 * 100.0) as u16
+    let progress_text = progress_text(track_info, app.time_display, tr);
+    let progress_block = Block::default()
+        .borders(Borders::ALL)
+        .title(tr.format("progress.title", &[("progress", &progress_text)]));
+    if track_info.is_live() {
+        f.render_widget(live_marker(tr).block(progress_block), track_chunks[4]);
     } else {
-        0
+        let progress_gauge = Gauge::default()
+            .block(progress_block)
+            .gauge_style(Style::default().fg(Color::Green))
+            .percent(progress_percent(track_info));
+        f.render_widget(progress_gauge, track_chunks[4]);
+    }
+    app.button_positions.insert("progress".to_string(),
+        (track_chunks[4].x, track_chunks[4].y, track_chunks[4].width, track_chunks[4].height));
+
+    // Full metadata
+    if app.show_details {
+        let lines = detail_lines(track_info, tr);
+        app.details_scroll = app.details_scroll.min(lines.len().saturating_sub(1) as u16);
+        let details = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .scroll((app.details_scroll, 0))
+            .block(Block::default().borders(Borders::ALL).title(tr.get("details.title")));
+        f.render_widget(details, track_chunks[5]);
+    }
+}
+
+/// Heart for liked tracks, followed by the player's rating as stars if it has one
+fn like_label(track: &TrackInfo, liked: bool, tr: Catalog) -> Line<'static> {
+    let heart = if liked {
+        Span::styled("♥", Style::default().fg(Color::Red))
+    } else {
+        Span::raw("♡")
     };
-    
-    let position_str = format!("{:02}:{:02}", track_info.position / 60, track_info.position % 60);
-    let duration_str = format!("{:02}:{:02}", track_info.duration / 60, track_info.duration % 60);
-    let progress_text = format!("{} / {}", position_str, duration_str);
-    
-    let progress_gauge = Gauge::default()
-        .block(Block::default()
-            .borders(Borders::ALL)
-            .title(format!("Progress ({})", progress_text)))
-        .gauge_style(Style::default().fg(Color::Green))
-        .percent(progress);
-    f.render_widget(progress_gauge, track_chunks[4]);
+    let mut spans = vec![Span::raw(" "), heart];
+    if let Some(rating) = track.user_rating {
+        spans.push(Span::styled(format!(" {}", stars(rating)), Style::default().fg(Color::Yellow)));
+    }
+    spans.push(Span::raw(format!(" {} ", tr.get("key.like"))));
+    Line::from(spans)
+}
 
-    // Clickable Controls
-    let control_chunks = Layout::default()
+/// A 0.0 to 1.0 rating as five stars
+fn stars(rating: f64) -> String {
+    let stars = (rating.clamp(0.0, 1.0) * 5.0).round() as usize;
+    format!("{}{}", "★".repeat(stars), "☆".repeat(5 - stars))
+}
+
+/// Liked tracks of players that cannot rate, newest first
+fn render_favorites(f: &mut Frame, app: &App, area: Rect) {
+    let favorites_block = Block::default()
+        .borders(Borders::ALL)
+        .title(app.tr.get("favorites.title"))
+        .title_style(Style::default().fg(Color::Yellow));
+
+    let line_width = favorites_block.inner(area).width as usize;
+    let lines: Vec<Line> = app
+        .music_player
+        .favorites()
+        .list()
+        .map(|favorite| {
+            let mut spans = vec![
+                Span::styled("♥ ", Style::default().fg(Color::Red)),
+                Span::styled(favorite.title.clone(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD)),
+            ];
+            if !favorite.artist.is_empty() {
+                spans.push(Span::styled(format!(" - {}", favorite.artist), Style::default().fg(Color::Cyan)));
+            }
+            if let Some(album) = &favorite.album {
+                spans.push(Span::styled(format!(" ({})", album), Style::default().fg(Color::Magenta)));
+            }
+            spans.push(Span::styled(
+                format!("  {}", app.tr.format("favorites.heard_on", &[("player", &favorite.player)])),
+                Style::default().fg(Color::DarkGray),
+            ));
+            text::truncate_line(Line::from(spans), line_width)
+        })
+        .collect();
+
+    if lines.is_empty() {
+        let placeholder = Paragraph::new(app.tr.get("favorites.empty"))
+            .style(Style::default().fg(Color::Blue))
+            .alignment(Alignment::Center)
+            .block(favorites_block);
+        f.render_widget(placeholder, area);
+        return;
+    }
+    f.render_widget(Paragraph::new(lines).block(favorites_block), area);
+}
+
+/// Cells of the small covers on the dashboard cards
+const CARD_COVER_SIZE: (u16, u16) = (12, 5);
+
+/// One card per player, for seeing and controlling every player at once
+fn render_dashboard(f: &mut Frame, app: &mut App, area: Rect) {
+    const CARD_HEIGHT: u16 = 7;
+
+    let dashboard_block = Block::default()
+        .borders(Borders::ALL)
+        .title(app.tr.get("dashboard.title"))
+        .title_style(Style::default().fg(Color::Yellow));
+    let inner_area = dashboard_block.inner(area);
+    f.render_widget(dashboard_block, area);
+
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1), // Actions
+            Constraint::Min(0),    // Cards
+        ])
+        .split(inner_area);
+
+    // Pause all others action
+    let pause_others_text = app.tr.get("dashboard.pause_others");
+    let pause_others = Rect { width: rows[0].width.min(text::width(pause_others_text) as u16), ..rows[0] };
+    app.button_positions.insert("pause_others".to_string(),
+        (pause_others.x, pause_others.y, pause_others.width, pause_others.height));
+    let pause_others_button = Paragraph::new(pause_others_text)
+        .style(Style::default().fg(Color::Red));
+    f.render_widget(pause_others_button, pause_others);
+
+    let players = app.music_player.get_players().to_vec();
+    if rows[1].height == 0 {
+        return;
+    }
+    if players.is_empty() {
+        let placeholder = Paragraph::new(app.tr.get("dashboard.empty"))
+            .style(Style::default().fg(Color::Blue))
+            .alignment(Alignment::Center);
+        f.render_widget(placeholder, rows[1]);
+        return;
+    }
+
+    // Two columns of cards on wide terminals
+    let columns = if rows[1].width >= 100 { 2 } else { 1 };
+    let card_width = rows[1].width / columns as u16;
+    let current_id = app.music_player.get_current_player().map(|p| p.id.clone());
+
+    // Cards that do not fit scroll, keeping the focused player in view
+    let total_rows = players.len().div_ceil(columns);
+    let mut cards_area = rows[1];
+    let mut visible_rows = (cards_area.height / CARD_HEIGHT) as usize;
+    // Below a card's height only the line saying how many are hidden is left
+    if total_rows > visible_rows {
+        cards_area.height = cards_area.height.saturating_sub(1);
+        visible_rows = (cards_area.height / CARD_HEIGHT) as usize;
+    }
+    let focused_row = players.iter().position(|p| current_id.as_ref() == Some(&p.entry.id)).unwrap_or(0) / columns;
+    if focused_row < app.dashboard_scroll {
+        app.dashboard_scroll = focused_row;
+    } else if focused_row >= app.dashboard_scroll + visible_rows {
+        app.dashboard_scroll = focused_row + 1 - visible_rows.max(1);
+    }
+    app.dashboard_scroll = app.dashboard_scroll.min(total_rows.saturating_sub(visible_rows));
+
+    let first = app.dashboard_scroll * columns;
+    let end = players.len().min(first + visible_rows * columns);
+    for (index, player) in players.iter().enumerate().take(end).skip(first) {
+        let y = cards_area.y + ((index - first) / columns) as u16 * CARD_HEIGHT;
+        let x = cards_area.x + (index % columns) as u16 * card_width;
+        let focused = current_id.as_ref() == Some(&player.entry.id);
+        render_player_card(f, app, player, Rect::new(x, y, card_width, CARD_HEIGHT), focused);
+    }
+
+    let hidden = players.len() - (end - first);
+    if hidden > 0 {
+        let more = Rect { y: cards_area.bottom(), height: 1, ..cards_area };
+        app.button_positions.insert("dashboard_more".to_string(), (more.x, more.y, more.width, more.height));
+        let more_text = app.tr.format("dashboard.more", &[("count", &hidden.to_string())]);
+        let more_line = Paragraph::new(text::truncate(&more_text, more.width as usize))
+            .style(Style::default().fg(Color::Yellow))
+            .alignment(Alignment::Center);
+        f.render_widget(more_line, more);
+    }
+}
+
+fn render_player_card(f: &mut Frame, app: &mut App, player: &PlayerState, area: Rect, focused: bool) {
+    let track = &player.track;
+    let id = &player.entry.id;
+
+    let (card_title, border_style) = if focused {
+        (format!("* {}", player.entry.identity), Style::default().fg(Color::Yellow))
+    } else {
+        (player.entry.identity.clone(), Style::default())
+    };
+    let card_block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(border_style)
+        .title(text::truncate(&card_title, area.width.saturating_sub(2) as usize));
+    let inner_area = card_block.inner(area);
+    f.render_widget(card_block, area);
+    app.button_positions.insert(format!("card:{}", id), (area.x, area.y, area.width, area.height));
+
+    let card_chunks = Layout::default()
         .direction(Direction::Horizontal)
         .constraints([
-            Constraint::Percentage(25), // Previous button
-            Constraint::Percentage(25), // Play/Pause button
-            Constraint::Percentage(25), // Next button
-            Constraint::Percentage(25), // Quit button
+            Constraint::Length(CARD_COVER_SIZE.0), // Cover
+            Constraint::Min(0),     // Track info
+            Constraint::Length(8),  // Play/Pause button
         ])
-        .split(chunks[2]);
+        .split(inner_area);
 
-    // Store button positions for mouse click detection
-    app.button_positions.insert("previous".to_string(), 
-        (control_chunks[0].x, control_chunks[0].y, control_chunks[0].width, control_chunks[0].height));
-    app.button_positions.insert("play_pause".to_string(), 
-        (control_chunks[1].x, control_chunks[1].y, control_chunks[1].width, control_chunks[1].height));
-    app.button_positions.insert("next".to_string(), 
-        (control_chunks[2].x, control_chunks[2].y, control_chunks[2].width, control_chunks[2].height));
-    app.button_positions.insert("quit".to_string(), 
-        (control_chunks[3].x, control_chunks[3].y, control_chunks[3].width, control_chunks[3].height));
+    // Small cover
+    if let Some((_, Some(image))) = app.card_covers.get_mut(id) {
+        image.render(f, card_chunks[0]);
+    } else {
+        let placeholder = Paragraph::new("\n♪")
+            .style(Style::default().fg(Color::Blue))
+            .alignment(Alignment::Center);
+        f.render_widget(placeholder, card_chunks[0]);
+    }
 
-    // Previous button
-    let prev_button = Paragraph::new("<< Previous")
-        .style(Style::default().fg(Color::Blue))
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL).title("P"));
-    f.render_widget(prev_button, control_chunks[0]);
+    // Status, track and progress
+    let info_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1), // Status
+            Constraint::Length(1), // Title
+            Constraint::Length(1), // Artist
+            Constraint::Length(1), // Progress
+            Constraint::Min(0),
+        ])
+        .split(card_chunks[1]);
+
+    let (status_text, status_color) = if track.is_playing {
+        (app.tr.get("card.playing"), Color::Green)
+    } else {
+        (app.tr.get("card.paused"), Color::DarkGray)
+    };
+    f.render_widget(Paragraph::new(status_text).style(Style::default().fg(status_color)), info_chunks[0]);
+    let info_width = info_chunks[1].width as usize;
+    let title_text = track.title.as_deref().unwrap_or(app.tr.get("unknown.title"));
+    let title = Paragraph::new(text::truncate(title_text, info_width))
+        .style(Style::default().fg(Color::White).add_modifier(Modifier::BOLD));
+    f.render_widget(title, info_chunks[1]);
+    let artist_text = track.artist_display();
+    let artist_text = artist_text.as_deref().unwrap_or(app.tr.get("unknown.artist"));
+    let artist = Paragraph::new(text::truncate(artist_text, info_width))
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(artist, info_chunks[2]);
+    if track.is_live() {
+        f.render_widget(live_marker(app.tr), info_chunks[3]);
+    } else {
+        let progress_gauge = Gauge::default()
+            .gauge_style(Style::default().fg(Color::Green))
+            .percent(progress_percent(track))
+            .label(progress_text(track, app.time_display, app.tr));
+        f.render_widget(progress_gauge, info_chunks[3]);
+    }
 
     // Play/Pause button
-    let play_pause_text = if track_info.is_playing { "|| Pause" } else { "> Play" };
-    let play_pause_button = Paragraph::new(play_pause_text)
-        .style(Style::default().fg(Color::Red))
+    let button_area = Rect { height: inner_area.height.min(3), ..card_chunks[2] };
+    app.button_positions.insert(format!("card_play:{}", id),
+        (button_area.x, button_area.y, button_area.width, button_area.height));
+    let play_pause_button = Paragraph::new(if track.is_playing { "||" } else { ">" })
+        .style(button_style(Color::Red, player.capabilities.can_control && player.capabilities.can_pause))
         .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL).title("SPACE"));
-    f.render_widget(play_pause_button, control_chunks[1]);
+        .block(Block::default().borders(Borders::ALL));
+    f.render_widget(play_pause_button, button_area);
+}
 
-    // Next button
-    let next_button = Paragraph::new("Next >>")
-        .style(Style::default().fg(Color::Green))
-        .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL).title("N"));
-    f.render_widget(next_button, control_chunks[2]);
+fn progress_percent(track: &TrackInfo) -> u16 {
+    if track.duration > 0 {
+        (track.position as f64 / track.duration as f64 * 100.0).min(100.0) as u16
+    } else {
+        0
+    }
+}
 
-    // Quit button
-    let quit_button = Paragraph::new("X Quit")
-        .style(Style::default().fg(Color::White))
+/// MM:SS, or H:MM:SS from an hour on. `long` forces H:MM:SS, so times
+/// shown next to an hour-long length line up with it.
+fn format_time(seconds: u64, long: bool) -> String {
+    if long || seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+fn progress_text(track: &TrackInfo, display: TimeDisplay, tr: Catalog) -> String {
+    // Streams have no length, only how long they have been playing
+    if track.is_live() {
+        let live = tr.get("progress.live");
+        return if track.position > 0 {
+            format!("{} {}", live, format_time(track.position, false))
+        } else {
+            live.to_string()
+        };
+    }
+
+    let long = track.duration >= 3600;
+    let rate = track.rate.map(|rate| (rate.current * 100.0).round() / 100.0);
+    match (display, rate) {
+        // Players that can change speed also get the time left at that speed
+        (TimeDisplay::Elapsed, Some(rate)) => format!(
+            "{} / {} [{}x, -{}]",
+            format_time(track.position, long),
+            format_time(track.duration, long),
+            rate,
+            format_time(track.remaining(), long)
+        ),
+        (TimeDisplay::Elapsed, None) => {
+            format!("{} / {}", format_time(track.position, long), format_time(track.duration, long))
+        }
+        (TimeDisplay::Remaining, Some(rate)) => format!("-{} [{}x]", format_time(track.remaining(), long), rate),
+        (TimeDisplay::Remaining, None) => format!("-{}", format_time(track.remaining(), long)),
+        (TimeDisplay::Percentage, Some(rate)) => format!("{}% [{}x]", progress_percent(track), rate),
+        (TimeDisplay::Percentage, None) => format!("{}%", progress_percent(track)),
+    }
+}
+
+/// Shown instead of a gauge for streams, which have no end to count towards
+fn live_marker(tr: Catalog) -> Paragraph<'static> {
+    Paragraph::new(format!("● {}", tr.get("progress.live")))
+        .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD))
         .alignment(Alignment::Center)
-        .block(Block::default().borders(Borders::ALL).title("Q"));
-    f.render_widget(quit_button, control_chunks[3]);
 }
 
 
+/// One line per metadata field the player provides, for the details panel
+fn detail_lines(track: &TrackInfo, tr: Catalog) -> Vec<Line<'static>> {
+    fn list(values: &[String]) -> Option<String> {
+        (!values.is_empty()).then(|| values.join(", "))
+    }
+
+    let rating = track.user_rating.map(|rating| format!("{} ({:.2})", stars(rating), rating));
+
+    let fields = [
+        (tr.get("detail.title"), track.title.clone()),
+        (tr.get("detail.artists"), list(&track.artists)),
+        (tr.get("detail.album"), track.album.clone()),
+        (tr.get("detail.album_artists"), list(&track.album_artists)),
+        (tr.get("detail.track"), track.track_number.map(|n| n.to_string())),
+        (tr.get("detail.disc"), track.disc_number.map(|n| n.to_string())),
+        (tr.get("detail.genre"), list(&track.genres)),
+        (tr.get("detail.year"), track.year.clone()),
+        (tr.get("detail.composer"), list(&track.composers)),
+        (tr.get("detail.url"), track.url.clone()),
+        (tr.get("detail.track_id"), track.track_id.clone()),
+        (tr.get("detail.rating"), rating),
+        (tr.get("detail.play_count"), track.play_count.map(|n| n.to_string())),
+    ];
+
+    let label_style = Style::default().fg(Color::Yellow);
+    fields
+        .into_iter()
+        .filter_map(|(label, value)| Some((label.to_string(), value?)))
+        .chain(track.extra.iter().cloned())
+        .map(|(label, value)| {
+            Line::from(vec![Span::styled(format!("{}: ", label), label_style), Span::raw(value)])
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use backend::fake::{FakeBackend, FakePlayer};
+    use mpris::PlaybackStatus;
+    use ratatui::backend::TestBackend;
+
+    fn render(app: &mut App) -> String {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| ui(f, app)).unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer
+            .content()
+            .chunks(buffer.area.width as usize)
+            .map(|row| row.iter().map(|cell| cell.symbol()).collect::<String>())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn foreground_at(app: &mut App, x: u16, y: u16) -> Color {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        terminal.draw(|f| ui(f, app)).unwrap();
+        terminal.backend().buffer()[(x, y)].fg
+    }
+
+    fn app_with(backend: &FakeBackend) -> App {
+        // Whatever the locale of the machine running the tests
+        let config = Config {
+            language: Some(Language::English),
+            ..Config::default()
+        };
+        let mut app = App::new(MusicPlayer::new(Box::new(backend.clone())), &config, LogBuffer::default());
+        app.music_player.update();
+        app
+    }
+
+    #[test]
+    fn ui_shows_current_track() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Fake Player")
+            .status(PlaybackStatus::Playing)
+            .with("xesam:title", "Fake Song")
+            .with("xesam:album", "Fake Album")
+            .with("mpris:length", 90_000_000i64)
+            .position(Duration::from_secs(30))]);
+        let screen = render(&mut app_with(&backend));
+
+        assert!(screen.contains("Connected to: Fake Player"));
+        assert!(screen.contains("> Fake Song"));
+        assert!(screen.contains("Unknown Artist"));
+        assert!(screen.contains("Fake Album"));
+        assert!(screen.contains("00:30 / 01:30"));
+        assert!(screen.contains("|| Pause"));
+    }
+
+    #[test]
+    fn progress_shows_rate_and_time_left() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Fake Player")
+            .with("mpris:length", 90_000_000i64)
+            .position(Duration::from_secs(30))
+            .rate(1.0, 0.25, 4.0)]);
+        let mut app = app_with(&backend);
+        assert!(render(&mut app).contains("00:30 / 01:30 [1x, -01:00]"));
+
+        app.on_key(KeyCode::Char(']'));
+        assert!(render(&mut app).contains("00:30 / 01:30 [1.25x, -00:48]"));
+    }
+
+    #[test]
+    fn progress_switches_between_elapsed_remaining_and_percentage() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Fake Player")
+            .with("mpris:length", 4_512_000_000i64)
+            .position(Duration::from_secs(1128))]);
+        let mut app = app_with(&backend);
+        assert!(render(&mut app).contains("Progress (0:18:48 / 1:15:12)"));
+
+        app.on_key(KeyCode::Char('t'));
+        assert!(render(&mut app).contains("Progress (-0:56:24)"));
+        app.on_key(KeyCode::Char('t'));
+        assert!(render(&mut app).contains("Progress (25%)"));
+
+        // Clicking the progress bar switches too, back to the start
+        let (x, y, _, _) = app.button_positions["progress"];
+        app.handle_button_click(x + 1, y + 1);
+        assert!(render(&mut app).contains("Progress (0:18:48 / 1:15:12)"));
+    }
+
+    #[test]
+    fn streams_without_length_are_live() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Radio")
+            .with("xesam:title", "Stream")
+            .position(Duration::from_secs(754))]);
+        let mut app = app_with(&backend);
+        let screen = render(&mut app);
+        assert!(screen.contains("Progress (LIVE 12:34)"));
+        assert!(screen.contains("● LIVE"));
+        assert!(!screen.contains("0%"));
+    }
+
+    #[test]
+    fn times_get_hours_from_an_hour_on() {
+        assert_eq!(format_time(59, false), "00:59");
+        assert_eq!(format_time(3599, false), "59:59");
+        assert_eq!(format_time(3600, false), "1:00:00");
+        assert_eq!(format_time(4512, false), "1:15:12");
+        assert_eq!(format_time(65, true), "0:01:05");
+    }
+
+    #[test]
+    fn ui_without_players() {
+        let screen = render(&mut app_with(&FakeBackend::default()));
+
+        assert!(screen.contains("No active player"));
+        assert!(screen.contains("No Cover"));
+        assert!(screen.contains("> Play"));
+        // Without a track there is no length either, which is not a stream
+        assert!(!screen.contains("LIVE"));
+        assert!(screen.contains("Progress (00:00 / 00:00)"));
+    }
+
+    #[test]
+    fn ui_without_session_bus() {
+        let backend = FakeBackend::default();
+        backend.state.borrow_mut().bus_down = true;
+        let screen = render(&mut app_with(&backend));
+
+        assert!(screen.contains("No D-Bus session bus"));
+        assert!(screen.contains("DBUS_SESSION_BUS_ADDRESS"));
+    }
+
+    #[test]
+    fn details_panel_lists_all_metadata() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A")
+            .with("xesam:title", "Song")
+            .with("xesam:artist", vec![mpris::MetadataValue::from("One"), mpris::MetadataValue::from("Two")])
+            .with("xesam:userRating", 0.6)
+            .with("xesam:comment", "Live")]);
+        let mut app = app_with(&backend);
+        assert!(render(&mut app).contains("One, Two"));
+        assert!(!render(&mut app).contains("Details"));
+
+        let (x, y, _, _) = app.button_positions["track"];
+        app.handle_button_click(x + 1, y + 1);
+        let screen = render(&mut app);
+        assert!(screen.contains("Artists: One, Two"));
+        assert!(screen.contains("Rating: ★★★☆☆ (0.60)"));
+        assert!(screen.contains("Track ID: /A/track/0"));
+        assert!(screen.contains("xesam:comment: Live"));
+    }
+
+    #[test]
+    fn ui_speaks_chinese() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Fake Player")
+            .status(PlaybackStatus::Playing)
+            .with("xesam:title", "晴天")]);
+        let mut app = app_with(&backend);
+        app.tr = Catalog::new(Language::SimplifiedChinese);
+        // Cells hidden behind wide characters render as spaces here
+        let screen = render(&mut app).replace(' ', "");
+
+        assert!(screen.contains("已连接：FakePlayer"));
+        assert!(screen.contains("未知艺术家"));
+        assert!(screen.contains("未知专辑"));
+        assert!(screen.contains("||暂停"));
+        assert!(screen.contains("下一首>>"));
+        assert!(screen.contains("空格"));
+        assert!(!screen.contains("Unknown") && !screen.contains("SPACE"));
+
+        app.on_key(KeyCode::Char('d'));
+        let screen = render(&mut app).replace(' ', "");
+        assert!(screen.contains("[暂停其他播放器]"));
+        assert!(screen.contains(">播放中"));
+    }
+
+    #[test]
+    fn wide_titles_are_cut_with_an_ellipsis() {
+        let title = format!("晴天 🌞 {} End", "七里香".repeat(20));
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "播放器")
+            .with("xesam:title", title.as_str())
+            .with("xesam:artist", vec![mpris::MetadataValue::from("周杰伦")])]);
+        let mut app = app_with(&backend);
+        app.on_key(KeyCode::Char('d'));
+        let screen = render(&mut app);
+
+        // Cells hidden behind wide characters render as spaces here
+        let compact: Vec<String> = screen.lines().map(|row| row.replace(' ', "")).collect();
+        let title_row = compact.iter().find(|row| row.contains("晴天🌞")).unwrap();
+        assert!(title_row.contains("…│"));
+        assert!(!title_row.contains("End"));
+        assert!(compact.iter().any(|row| row.contains("周杰伦")));
+        assert!(compact.iter().any(|row| row.contains("*播放器")));
+    }
+
+    #[test]
+    fn dashboard_shows_a_card_per_player() {
+        let backend = FakeBackend::with_players(vec![
+            FakePlayer::new("a", "Player A").with("xesam:title", "Song A"),
+            FakePlayer::new("b", "Player B")
+                .status(PlaybackStatus::Playing)
+                .with("xesam:title", "Song B"),
+        ]);
+        let mut app = app_with(&backend);
+        app.on_key(KeyCode::Char('d'));
+        let screen = render(&mut app);
+
+        assert!(screen.contains("* Player A"));
+        assert!(screen.contains("Player B"));
+        assert!(screen.contains("Song A"));
+        assert!(screen.contains("Song B"));
+        assert!(screen.contains("> Playing"));
+        assert!(screen.contains("Pause all others"));
+
+        // Play/Pause on a card goes to that card's player, clicking the card focuses it
+        let (x, y, _, _) = app.button_positions["card_play:b"];
+        app.handle_button_click(x + 1, y + 1);
+        let (x, y, _, _) = app.button_positions["card:b"];
+        app.handle_button_click(x + 1, y + 1);
+
+        assert_eq!(backend.sent(), vec![("b".to_string(), backend::PlayerCommand::PlayPause)]);
+        assert_eq!(app.music_player.get_current_player().unwrap().id, "b");
+        assert!(render(&mut app).contains("* Player B"));
+    }
+
+    #[test]
+    fn dashboard_scrolls_to_hidden_cards() {
+        let backend = FakeBackend::with_players(
+            ["a", "b", "c", "d"].iter().map(|id| FakePlayer::new(id, &format!("Player {}", id))).collect(),
+        );
+        let mut app = app_with(&backend);
+        app.on_key(KeyCode::Char('d'));
+        let screen = render(&mut app);
+        assert!(screen.contains("* Player a"));
+        assert!(screen.contains("Player b"));
+        assert!(!screen.contains("Player c"));
+        assert!(screen.contains("+2 more"));
+
+        // TAB focus scrolls the focused card into view
+        app.on_key(KeyCode::Tab);
+        app.on_key(KeyCode::Tab);
+        let screen = render(&mut app);
+        assert!(screen.contains("* Player c"));
+        assert!(!screen.contains("Player a"));
+        assert!(screen.contains("+2 more"));
+
+        // So does clicking the line
+        let (x, y, _, _) = app.button_positions["dashboard_more"];
+        app.handle_button_click(x + 1, y);
+        let screen = render(&mut app);
+        assert!(screen.contains("* Player d"));
+        assert!(!screen.contains("Player b"));
+    }
+
+    #[test]
+    fn dashboard_fits_small_terminals() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A"), FakePlayer::new("b", "B")]);
+        let mut app = app_with(&backend);
+        app.on_key(KeyCode::Char('d'));
+        for height in 1..12 {
+            let mut terminal = Terminal::new(TestBackend::new(60, height)).unwrap();
+            terminal.draw(|f| ui(f, &mut app)).unwrap();
+        }
+    }
+
+    #[test]
+    fn log_pane_toggles() {
+        let mut app = app_with(&FakeBackend::default());
+        assert!(!render(&mut app).contains("Log (L to hide)"));
+
+        app.on_key(KeyCode::Char('l'));
+        assert!(render(&mut app).contains("Log (L to hide)"));
+    }
+
+    #[test]
+    fn clicking_buttons_sends_commands() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A")]);
+        let mut app = app_with(&backend);
+        render(&mut app);
+
+        let (x, y, _, _) = app.button_positions["next"];
+        app.handle_button_click(x + 1, y + 1);
+        let (x, y, _, _) = app.button_positions["quit"];
+        app.handle_button_click(x + 1, y + 1);
+
+        assert_eq!(backend.sent(), vec![("a".to_string(), backend::PlayerCommand::Next)]);
+        assert!(app.should_quit);
+    }
+
+    #[test]
+    fn quitting_a_player_asks_first() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Fake Player")]);
+        let mut app = app_with(&backend);
+
+        app.on_key(KeyCode::Char('Q'));
+        assert!(render(&mut app).contains("Close Fake Player?"));
+        app.on_key(KeyCode::Char('n'));
+        assert!(!render(&mut app).contains("Close Fake Player?"));
+        assert!(backend.sent().is_empty());
+
+        app.on_key(KeyCode::Char('Q'));
+        render(&mut app);
+        let (x, y, _, _) = app.button_positions["confirm_quit"];
+        app.handle_button_click(x, y);
+        app.on_key(KeyCode::Char('s'));
+        app.on_key(KeyCode::Char('w'));
+        assert_eq!(
+            backend.sent(),
+            vec![
+                ("a".to_string(), backend::PlayerCommand::Quit),
+                ("a".to_string(), backend::PlayerCommand::Stop),
+                ("a".to_string(), backend::PlayerCommand::Raise),
+            ]
+        );
+        assert!(!app.should_quit);
+    }
+
+    #[test]
+    fn players_that_cannot_quit_are_not_asked() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Browser").capabilities(
+            backend::Capabilities {
+                can_quit: false,
+                ..backend::Capabilities::default()
+            },
+        )]);
+        let mut app = app_with(&backend);
+
+        app.on_key(KeyCode::Char('Q'));
+        let screen = render(&mut app);
+        assert!(!screen.contains("Close Browser?"));
+        assert!(screen.contains("Browser does not support quitting"));
+    }
+
+    #[test]
+    fn open_prompt_sends_supported_uris() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Fake Player").uri_schemes(&["https"])]);
+        let mut app = app_with(&backend);
+
+        app.on_key(KeyCode::Char('u'));
+        for c in "spotify:track:1".chars() {
+            app.on_key(KeyCode::Char(c));
+        }
+        app.on_key(KeyCode::Enter);
+        let screen = render(&mut app);
+        assert!(screen.contains("> spotify:track:1"));
+        assert!(screen.contains("does not support spotify: URIs"));
+        assert!(backend.sent().is_empty());
+
+        app.on_key(KeyCode::Esc);
+        app.on_key(KeyCode::Char('u'));
+        for c in "https://example.com/a.mp3".chars() {
+            app.on_key(KeyCode::Char(c));
+        }
+        app.on_key(KeyCode::Enter);
+        assert!(app.open_prompt.is_none());
+        assert_eq!(
+            backend.sent(),
+            vec![("a".to_string(), backend::PlayerCommand::OpenUri("https://example.com/a.mp3".to_string()))]
+        );
+    }
+
+    #[test]
+    fn unsupported_commands_are_disabled() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Radio").capabilities(
+            backend::Capabilities {
+                can_go_next: false,
+                ..backend::Capabilities::default()
+            },
+        )]);
+        let mut app = app_with(&backend);
+        render(&mut app);
+
+        let (x, y, _, _) = app.button_positions["next"];
+        assert_eq!(foreground_at(&mut app, x + 3, y + 1), Color::DarkGray);
+        app.handle_button_click(x + 1, y + 1);
+
+        assert!(backend.sent().is_empty());
+        assert!(render(&mut app).contains("Radio does not support next track"));
+    }
+
+    #[test]
+    fn long_titles_scroll_until_clicked() {
+        let title = format!("Start {} End", "长".repeat(30));
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Player").with("xesam:title", title.as_str())]);
+        let mut app = app_with(&backend);
+        let screen = render(&mut app);
+        assert!(screen.contains("Start"));
+        assert!(!screen.contains("End"));
+
+        // 2s at the start, then 4 columns a second to the end
+        for _ in 0..26 {
+            app.marquees.tick(std::time::Duration::from_millis(250));
+        }
+        let screen = render(&mut app);
+        assert!(!screen.contains("Start"));
+        assert!(screen.contains("End"));
+
+        // Clicking stops it where it is
+        let (x, y, _, _) = app.button_positions["marquee:title"];
+        app.handle_button_click(x, y);
+        for _ in 0..40 {
+            app.marquees.tick(std::time::Duration::from_millis(250));
+        }
+        assert_eq!(render(&mut app), screen);
+    }
+
+    #[test]
+    fn cover_zooms_to_full_screen() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Player")
+            .with("xesam:title", "Zoomed Song")
+            .with("xesam:artist", vec![mpris::MetadataValue::from("Zoomed Artist")])]);
+        let mut app = app_with(&backend);
+        render(&mut app);
+
+        let (x, y, _, _) = app.button_positions["cover"];
+        app.handle_button_click(x + 2, y + 2);
+        let screen = render(&mut app);
+        assert!(screen.contains("♪ Zoomed Song - Zoomed Artist"));
+        assert!(screen.contains("Z/Esc: close"));
+        assert!(!screen.contains("Next >>"));
+
+        app.on_key(KeyCode::Esc);
+        assert!(render(&mut app).contains("Next >>"));
+    }
+
+    #[test]
+    fn liked_tracks_show_up_in_favorites() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Browser")
+            .with("xesam:title", "Liked Song")
+            .with("xesam:artist", vec![mpris::MetadataValue::from("Some Artist")])]);
+        let mut app = app_with(&backend);
+        assert!(render(&mut app).contains("♡ F"));
+
+        let (x, y, _, _) = app.button_positions["like"];
+        app.handle_button_click(x + 1, y);
+        let screen = render(&mut app);
+        assert!(screen.contains("♥ F"));
+        assert!(screen.contains("Added \"Liked Song\" to favorites"));
+
+        app.on_key(KeyCode::Char('F'));
+        assert!(render(&mut app).contains("♥ Liked Song - Some Artist  heard on Browser"));
+    }
+
+    #[test]
+    fn rating_shows_as_stars() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Player")
+            .with("xesam:title", "Rated Song")
+            .with("xesam:userRating", 0.6)]);
+        let screen = render(&mut app_with(&backend));
+        // Only the top rating is a like
+        assert!(screen.contains("♡ ★★★☆☆ F"));
+    }
+}
@@ -0,0 +1,278 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, time::Duration};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+use crate::text;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MarqueeConfig {
+    /// Scroll text that does not fit; it is cut off otherwise
+    pub enabled: bool,
+    /// Columns per second
+    pub speed: f64,
+    /// How long to wait at the start and at the end
+    pub pause_ms: u64,
+}
+
+impl Default for MarqueeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            speed: 4.0,
+            pause_ms: 2000,
+        }
+    }
+}
+
+impl MarqueeConfig {
+    fn pause(&self) -> Duration {
+        Duration::from_millis(self.pause_ms)
+    }
+}
+
+/// Scroll state of one text field
+#[derive(Debug)]
+struct Marquee {
+    text: String,
+    /// Columns the text is wider than its area, 0 if it fits
+    overflow: usize,
+    /// Columns scrolled so far
+    offset: f64,
+    /// Time left to wait before moving on
+    pause: Duration,
+    at_end: bool,
+    /// Stopped by a click
+    stopped: bool,
+}
+
+impl Marquee {
+    fn new(text: &str, pause: Duration) -> Self {
+        Self {
+            text: text.to_string(),
+            overflow: 0,
+            offset: 0.0,
+            pause,
+            at_end: false,
+            stopped: false,
+        }
+    }
+
+    fn advance(&mut self, elapsed: Duration, config: &MarqueeConfig) {
+        if !self.pause.is_zero() {
+            self.pause = self.pause.saturating_sub(elapsed);
+        } else if self.at_end {
+            // Back to the start, and wait there again
+            self.offset = 0.0;
+            self.at_end = false;
+            self.pause = config.pause();
+        } else {
+            self.offset += config.speed.max(0.0) * elapsed.as_secs_f64();
+            if self.offset >= self.overflow as f64 {
+                self.offset = self.overflow as f64;
+                self.at_end = true;
+                self.pause = config.pause();
+            }
+        }
+    }
+}
+
+/// Text fields that scroll when they are too long for their area, moved on
+/// by the UI tick. Fields are named by the UI, e.g. "title".
+#[derive(Debug, Default)]
+pub struct Marquees {
+    config: MarqueeConfig,
+    items: HashMap<String, Marquee>,
+    /// Field under the mouse, which holds still while it is there
+    hovered: Option<String>,
+}
+
+impl Marquees {
+    pub fn new(config: MarqueeConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    /// The part of `text` to show in `width` columns for field `name`
+    pub fn view(&mut self, name: &str, text: &str, width: u16) -> String {
+        let pause = self.config.pause();
+        let item = self.items.entry(name.to_string()).or_insert_with(|| Marquee::new(text, pause));
+        if item.text != text {
+            // Another track, start over
+            *item = Marquee::new(text, pause);
+        }
+        item.overflow = text::width(text).saturating_sub(width as usize);
+        if !self.config.enabled {
+            return text::truncate(text, width as usize);
+        }
+        if item.overflow == 0 {
+            return text.to_string();
+        }
+        window(text, item.offset.min(item.overflow as f64) as usize, width as usize)
+    }
+
+    /// Whether field `name` is too long for its area and scrolls
+    pub fn scrolls(&self, name: &str) -> bool {
+        self.config.enabled && self.items.get(name).is_some_and(|item| item.overflow > 0)
+    }
+
+    /// Stop field `name`, or let it scroll again
+    pub fn toggle(&mut self, name: &str) {
+        if let Some(item) = self.items.get_mut(name) {
+            item.stopped = !item.stopped;
+        }
+    }
+
+    pub fn set_hovered(&mut self, name: Option<&str>) {
+        self.hovered = name.map(str::to_string);
+    }
+
+    pub fn tick(&mut self, elapsed: Duration) {
+        if !self.config.enabled {
+            return;
+        }
+        for (name, item) in self.items.iter_mut() {
+            if item.overflow == 0 || item.stopped || self.hovered.as_ref() == Some(name) {
+                continue;
+            }
+            item.advance(elapsed, &self.config);
+        }
+    }
+}
+
+/// The columns of `text` from `offset` on that fit in `width`. Wide
+/// characters cut by either edge are replaced by spaces, so the result is
+/// never wider than `width` and keeps its position.
+fn window(text: &str, offset: usize, width: usize) -> String {
+    let mut shown = String::new();
+    let mut column = 0;
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let start = column;
+        column += grapheme.width();
+        if column <= offset {
+            continue;
+        }
+        let visible = column - start.max(offset);
+        if start < offset || used + visible > width {
+            // Half of a wide character
+            let room = visible.min(width - used);
+            shown.push_str(&" ".repeat(room));
+            used += room;
+        } else {
+            shown.push_str(grapheme);
+            used += visible;
+        }
+        if used >= width && column > offset + width {
+            break;
+        }
+    }
+    shown
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICK: Duration = Duration::from_millis(250);
+
+    fn marquees() -> Marquees {
+        Marquees::new(MarqueeConfig {
+            enabled: true,
+            speed: 4.0,
+            pause_ms: 500,
+        })
+    }
+
+    #[test]
+    fn windows_keep_wide_characters_whole() {
+        assert_eq!(window("abcdef", 2, 3), "cde");
+        // 你 and 好 are two columns each
+        assert_eq!(window("你好世界", 0, 4), "你好");
+        assert_eq!(window("你好世界", 1, 4), " 好 ");
+        assert_eq!(window("你好世界", 2, 3), "好 ");
+        assert_eq!(window("a🎵b", 1, 3), "🎵b");
+        assert_eq!(window("a🎵b", 2, 2), " b");
+        assert_eq!(window("cafe\u{301}s", 3, 2), "e\u{301}s");
+        for offset in 0..6 {
+            assert!(window("你好世界 music", offset, 5).width() <= 5);
+        }
+    }
+
+    #[test]
+    fn scrolls_with_a_pause_at_both_ends() {
+        let mut marquees = marquees();
+        let text = "0123456789";
+        assert_eq!(marquees.view("title", text, 6), "012345");
+        assert!(marquees.scrolls("title"));
+
+        // Waits at the start
+        marquees.tick(TICK);
+        marquees.tick(TICK);
+        assert_eq!(marquees.view("title", text, 6), "012345");
+        // One column per tick
+        marquees.tick(TICK);
+        assert_eq!(marquees.view("title", text, 6), "123456");
+        for _ in 0..3 {
+            marquees.tick(TICK);
+        }
+        assert_eq!(marquees.view("title", text, 6), "456789");
+        // Waits at the end, then jumps back
+        marquees.tick(TICK);
+        marquees.tick(TICK);
+        assert_eq!(marquees.view("title", text, 6), "456789");
+        marquees.tick(TICK);
+        assert_eq!(marquees.view("title", text, 6), "012345");
+    }
+
+    #[test]
+    fn short_text_and_new_text_do_not_scroll() {
+        let mut marquees = marquees();
+        assert_eq!(marquees.view("album", "short", 10), "short");
+        assert!(!marquees.scrolls("album"));
+
+        marquees.view("title", "0123456789", 6);
+        for _ in 0..4 {
+            marquees.tick(TICK);
+        }
+        assert_eq!(marquees.view("title", "0123456789", 6), "234567");
+        // A new track starts at the beginning
+        assert_eq!(marquees.view("title", "abcdefghij", 6), "abcdef");
+    }
+
+    #[test]
+    fn hover_and_click_stop_scrolling() {
+        let mut marquees = marquees();
+        marquees.view("title", "0123456789", 6);
+        marquees.view("artist", "0123456789", 6);
+        marquees.set_hovered(Some("title"));
+        marquees.toggle("artist");
+        for _ in 0..4 {
+            marquees.tick(TICK);
+        }
+        assert_eq!(marquees.view("title", "0123456789", 6), "012345");
+        assert_eq!(marquees.view("artist", "0123456789", 6), "012345");
+
+        marquees.set_hovered(None);
+        marquees.toggle("artist");
+        // Both still wait at the start first
+        for _ in 0..3 {
+            marquees.tick(TICK);
+        }
+        assert_eq!(marquees.view("title", "0123456789", 6), "123456");
+        assert_eq!(marquees.view("artist", "0123456789", 6), "123456");
+    }
+
+    #[test]
+    fn disabled_marquees_truncate_instead() {
+        let mut marquees = Marquees::new(MarqueeConfig {
+            enabled: false,
+            ..MarqueeConfig::default()
+        });
+        assert_eq!(marquees.view("title", "0123456789", 6), "01234…");
+        assert!(!marquees.scrolls("title"));
+    }
+}
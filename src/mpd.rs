@@ -0,0 +1,811 @@
+use anyhow::{anyhow, bail, Context, Result};
+use log::{debug, error, info};
+use mpris::{Metadata, MetadataValue, PlaybackStatus};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    os::unix::net::UnixStream,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Weak,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::backend::{Capabilities, PlaybackRate, PlayerBackend, PlayerCommand, PlayerEntry};
+use crate::uri;
+
+/// How long to wait before trying to reach MPD again
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Replies, and connecting, slower than this count as a dead connection
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Subsystems whose changes make the backend read the status again
+const IDLE_COMMAND: &str = "idle player mixer playlist options sticker";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MpdConfig {
+    /// Show MPD next to the MPRIS players
+    pub enabled: bool,
+    /// "host:port", or the path of MPD's Unix socket
+    pub address: String,
+    pub password: Option<String>,
+}
+
+impl Default for MpdConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            address: "localhost:6600".to_string(),
+            password: None,
+        }
+    }
+}
+
+/// Connect to the first address `address` resolves to that answers in time;
+/// a host that drops packets would otherwise block for minutes
+fn connect_tcp(address: &str) -> Result<TcpStream> {
+    let mut last_error = None;
+    for addr in address.to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, COMMAND_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(match last_error {
+        Some(e) => e.into(),
+        None => anyhow!("{} does not resolve to any address", address),
+    })
+}
+
+/// A TCP or Unix socket connection
+enum Stream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+}
+
+impl Stream {
+    fn connect(address: &str) -> Result<Self> {
+        let stream = if address.starts_with('/') || address.starts_with('~') {
+            let stream = UnixStream::connect(uri::expand_home(address))?;
+            stream.set_read_timeout(Some(COMMAND_TIMEOUT))?;
+            stream.set_write_timeout(Some(COMMAND_TIMEOUT))?;
+            Stream::Unix(stream)
+        } else {
+            let stream = connect_tcp(address)?;
+            stream.set_read_timeout(Some(COMMAND_TIMEOUT))?;
+            stream.set_write_timeout(Some(COMMAND_TIMEOUT))?;
+            Stream::Tcp(stream)
+        };
+        Ok(stream)
+    }
+
+    fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.set_read_timeout(timeout)?,
+            Stream::Unix(stream) => stream.set_read_timeout(timeout)?,
+        }
+        Ok(())
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.read(buf),
+            Stream::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        match self {
+            Stream::Tcp(stream) => stream.write(buf),
+            Stream::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        match self {
+            Stream::Tcp(stream) => stream.flush(),
+            Stream::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+/// An `ACK` reply: MPD refused the command, the connection is still fine
+#[derive(Debug)]
+struct Rejected(String);
+
+impl fmt::Display for Rejected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Rejected {}
+
+/// One client connection speaking the MPD text protocol
+struct Connection {
+    reader: BufReader<Stream>,
+}
+
+impl Connection {
+    fn open(config: &MpdConfig) -> Result<Self> {
+        let stream = Stream::connect(&config.address)
+            .with_context(|| format!("Failed to connect to MPD at {}", config.address))?;
+        let mut connection = Self {
+            reader: BufReader::new(stream),
+        };
+
+        let mut greeting = String::new();
+        connection.reader.read_line(&mut greeting)?;
+        if !greeting.starts_with("OK MPD ") {
+            bail!("{} is not MPD: {}", config.address, greeting.trim());
+        }
+        if let Some(password) = &config.password {
+            connection.command(&format!("password {}", quote(password)))?;
+        }
+        Ok(connection)
+    }
+
+    /// Send `command` and collect the "key: value" pairs of the reply
+    fn command(&mut self, command: &str) -> Result<Vec<(String, String)>> {
+        let stream = self.reader.get_mut();
+        stream.write_all(command.as_bytes())?;
+        stream.write_all(b"\n")?;
+        stream.flush()?;
+
+        let mut pairs = Vec::new();
+        loop {
+            let mut line = String::new();
+            if self.reader.read_line(&mut line)? == 0 {
+                bail!("MPD closed the connection");
+            }
+            let line = line.trim_end_matches('\n');
+            if line == "OK" {
+                return Ok(pairs);
+            }
+            if let Some(error) = line.strip_prefix("ACK ") {
+                return Err(Rejected(format!("MPD rejected {:?}: {}", command, error)).into());
+            }
+            if let Some((key, value)) = line.split_once(": ") {
+                pairs.push((key.to_string(), value.to_string()));
+            }
+        }
+    }
+}
+
+/// Quote an argument for the MPD protocol
+fn quote(argument: &str) -> String {
+    format!("\"{}\"", argument.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `status` and `currentsong` as last read
+#[derive(Debug, Default)]
+struct MpdState {
+    status: HashMap<String, String>,
+    song: Vec<(String, String)>,
+    /// The song's "rating" sticker, 0 to 10
+    rating: Option<u8>,
+    /// When `status` was read, to advance the position while playing
+    read_at: Option<Instant>,
+}
+
+impl MpdState {
+    fn status(&self, key: &str) -> Option<&str> {
+        self.status.get(key).map(String::as_str)
+    }
+
+    fn song(&self, key: &str) -> Option<&str> {
+        self.song.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str())
+    }
+
+    fn song_all(&self, key: &str) -> Vec<String> {
+        self.song.iter().filter(|(k, _)| k == key).map(|(_, v)| v.clone()).collect()
+    }
+
+    fn playback_status(&self) -> PlaybackStatus {
+        match self.status("state") {
+            Some("play") => PlaybackStatus::Playing,
+            Some("pause") => PlaybackStatus::Paused,
+            _ => PlaybackStatus::Stopped,
+        }
+    }
+
+    fn position(&self) -> Duration {
+        let elapsed = self
+            .status("elapsed")
+            .and_then(|s| s.parse::<f64>().ok())
+            .map(Duration::from_secs_f64)
+            .unwrap_or_default();
+        match (self.playback_status(), self.read_at) {
+            (PlaybackStatus::Playing, Some(at)) => elapsed + at.elapsed(),
+            _ => elapsed,
+        }
+    }
+
+    fn metadata(&self) -> Metadata {
+        let mut values: HashMap<String, MetadataValue> = HashMap::new();
+        let Some(file) = self.song("file") else {
+            return Metadata::default();
+        };
+        let id = self.song("Id").unwrap_or("0");
+        values.insert("mpris:trackid".to_string(), format!("/org/musicpd/song/{}", id).into());
+        values.insert("xesam:url".to_string(), file.to_string().into());
+        // Streams often only have a name
+        let title = self
+            .song("Title")
+            .or(self.song("Name"))
+            .map(str::to_string)
+            .unwrap_or_else(|| file.rsplit('/').next().unwrap_or(file).to_string());
+        values.insert("xesam:title".to_string(), title.into());
+
+        let lists = [
+            ("Artist", "xesam:artist"),
+            ("AlbumArtist", "xesam:albumArtist"),
+            ("Genre", "xesam:genre"),
+            ("Composer", "xesam:composer"),
+        ];
+        for (tag, key) in lists {
+            let values_for_tag = self.song_all(tag);
+            if !values_for_tag.is_empty() {
+                let list: Vec<MetadataValue> = values_for_tag.into_iter().map(MetadataValue::from).collect();
+                values.insert(key.to_string(), list.into());
+            }
+        }
+        if let Some(album) = self.song("Album") {
+            values.insert("xesam:album".to_string(), album.to_string().into());
+        }
+        if let Some(date) = self.song("Date") {
+            values.insert("xesam:contentCreated".to_string(), date.to_string().into());
+        }
+        // "1/12" style numbers
+        let number = |tag: &str| self.song(tag)?.split('/').next()?.trim().parse::<i32>().ok();
+        if let Some(track) = number("Track") {
+            values.insert("xesam:trackNumber".to_string(), track.into());
+        }
+        if let Some(disc) = number("Disc") {
+            values.insert("xesam:discNumber".to_string(), disc.into());
+        }
+        if let Some(rating) = self.rating {
+            values.insert("xesam:userRating".to_string(), (rating as f64 / 10.0).into());
+        }
+        let duration = self
+            .song("duration")
+            .or(self.status("duration"))
+            .or(self.song("Time"))
+            .and_then(|s| s.parse::<f64>().ok());
+        if let Some(duration) = duration {
+            values.insert("mpris:length".to_string(), ((duration * 1_000_000.0) as i64).into());
+        }
+        values.into()
+    }
+}
+
+fn url_schemes(handlers: Vec<(String, String)>) -> Vec<String> {
+    handlers
+        .into_iter()
+        .filter_map(|(_, handler)| handler.strip_suffix("://").map(str::to_string))
+        .collect()
+}
+
+/// Waits for changes on its own connection and flags them for the backend
+fn watch(config: MpdConfig, changed: Weak<AtomicBool>) {
+    loop {
+        let result = Connection::open(&config).and_then(|mut connection| {
+            // Idle blocks until something changes
+            connection.reader.get_ref().set_read_timeout(None)?;
+            loop {
+                let subsystems = connection.command(IDLE_COMMAND)?;
+                debug!("MPD changed: {:?}", subsystems);
+                match changed.upgrade() {
+                    Some(changed) => changed.store(true, Ordering::SeqCst),
+                    None => return Ok(()),
+                }
+            }
+        });
+        let Some(flag) = changed.upgrade() else {
+            return;
+        };
+        if let Err(e) = result {
+            debug!("MPD idle connection: {:#}", e);
+        }
+        // Whatever happened in between, read everything again
+        flag.store(true, Ordering::SeqCst);
+        drop(flag);
+        thread::sleep(RECONNECT_INTERVAL);
+    }
+}
+
+/// MPD, shown as one more player.
+pub struct MpdBackend {
+    config: MpdConfig,
+    id: String,
+    connection: Option<Connection>,
+    state: MpdState,
+    /// Set by the idle thread when MPD reports a change
+    changed: Arc<AtomicBool>,
+    watching: bool,
+    /// Cleared when MPD runs without a sticker database
+    stickers: bool,
+    /// What `urlhandlers` listed on the first connection, without the "://"
+    url_schemes: Option<Vec<String>>,
+    last_attempt: Option<Instant>,
+    last_error: Option<String>,
+}
+
+impl MpdBackend {
+    pub fn new(config: MpdConfig) -> Self {
+        Self {
+            id: format!("mpd:{}", config.address),
+            config,
+            connection: None,
+            state: MpdState::default(),
+            changed: Arc::new(AtomicBool::new(true)),
+            watching: false,
+            stickers: true,
+            url_schemes: None,
+            last_attempt: None,
+            last_error: None,
+        }
+    }
+
+    fn connect(&mut self) -> Result<&mut Connection> {
+        if self.connection.is_none() {
+            if let (Some(at), Some(err)) = (self.last_attempt, &self.last_error) {
+                if at.elapsed() < RECONNECT_INTERVAL {
+                    bail!("{}", err);
+                }
+            }
+
+            self.last_attempt = Some(Instant::now());
+            match Connection::open(&self.config) {
+                Ok(mut connection) => {
+                    info!("Connected to MPD at {}", self.config.address);
+                    if self.url_schemes.is_none() {
+                        match connection.command("urlhandlers") {
+                            Ok(handlers) => self.url_schemes = Some(url_schemes(handlers)),
+                            Err(e) => debug!("MPD did not list its URL handlers: {:#}", e),
+                        }
+                    }
+                    self.connection = Some(connection);
+                    self.last_error = None;
+                }
+                Err(e) => {
+                    let message = format!("{:#}", e);
+                    if self.last_error.as_ref() != Some(&message) {
+                        error!("{}", message);
+                    }
+                    self.last_error = Some(message);
+                    return Err(e);
+                }
+            }
+        }
+        if !self.watching {
+            let config = self.config.clone();
+            let changed = Arc::downgrade(&self.changed);
+            thread::Builder::new()
+                .name("mpd-idle".to_string())
+                .spawn(move || watch(config, changed))?;
+            self.watching = true;
+        }
+
+        Ok(self.connection.as_mut().unwrap())
+    }
+
+    /// Run `command`, dropping the connection if it fails so the next call reconnects
+    fn command(&mut self, command: &str) -> Result<Vec<(String, String)>> {
+        let result = self.connect()?.command(command);
+        if let Err(e) = &result {
+            if !e.is::<Rejected>() {
+                self.connection = None;
+            }
+        }
+        result
+    }
+
+    /// Read `status` and `currentsong` again if MPD reported a change
+    fn refresh(&mut self) -> Result<()> {
+        if !self.changed.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        let result = self.command("status").and_then(|status| {
+            let song = self.command("currentsong")?;
+            let rating = match song.iter().find(|(key, _)| key == "file") {
+                Some((_, file)) => self.read_rating(file)?,
+                None => None,
+            };
+            self.state = MpdState {
+                status: status.into_iter().collect(),
+                song,
+                rating,
+                read_at: Some(Instant::now()),
+            };
+            Ok(())
+        });
+        if result.is_err() {
+            self.changed.store(true, Ordering::SeqCst);
+        }
+        result
+    }
+
+    /// The "rating" sticker of `file`, the same one other MPD clients use
+    fn read_rating(&mut self, file: &str) -> Result<Option<u8>> {
+        if !self.stickers {
+            return Ok(None);
+        }
+        match self.command(&format!("sticker get song {} rating", quote(file))) {
+            // "sticker: rating=8"
+            Ok(pairs) => Ok(pairs
+                .iter()
+                .find_map(|(_, value)| value.strip_prefix("rating="))
+                .and_then(|rating| rating.parse::<u8>().ok())
+                .map(|rating| rating.min(10))),
+            Err(e) if e.is::<Rejected>() => {
+                if !e.to_string().contains("no such sticker") {
+                    info!("MPD ratings are not available: {}", e);
+                    self.stickers = false;
+                }
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn check_id(&self, id: &str) -> Result<()> {
+        if id != self.id {
+            bail!("Player {} is no longer available", id);
+        }
+        Ok(())
+    }
+}
+
+impl PlayerBackend for MpdBackend {
+    fn find_all(&mut self) -> Result<Vec<PlayerEntry>> {
+        self.refresh()?;
+        Ok(vec![PlayerEntry {
+            id: self.id.clone(),
+            identity: "MPD".to_string(),
+        }])
+    }
+
+    fn playback_status(&self, id: &str) -> Result<PlaybackStatus> {
+        self.check_id(id)?;
+        Ok(self.state.playback_status())
+    }
+
+    fn metadata(&self, id: &str) -> Result<Metadata> {
+        self.check_id(id)?;
+        Ok(self.state.metadata())
+    }
+
+    fn position(&self, id: &str) -> Result<Duration> {
+        self.check_id(id)?;
+        Ok(self.state.position())
+    }
+
+    fn rate(&self, id: &str) -> Result<Option<PlaybackRate>> {
+        self.check_id(id)?;
+        Ok(None)
+    }
+
+    fn volume(&self, id: &str) -> Result<Option<f64>> {
+        self.check_id(id)?;
+        // -1 without a mixer
+        let volume = self.state.status("volume").and_then(|v| v.parse::<i32>().ok());
+        Ok(volume.filter(|v| *v >= 0).map(|v| v as f64 / 100.0))
+    }
+
+    fn capabilities(&self, id: &str) -> Result<Capabilities> {
+        self.check_id(id)?;
+        Ok(Capabilities {
+            can_raise: false,
+            can_quit: false,
+            can_rate: self.stickers,
+            ..Capabilities::default()
+        })
+    }
+
+    fn supported_uri_schemes(&self, id: &str) -> Result<Vec<String>> {
+        self.check_id(id)?;
+        // Asked once on connecting, `urlhandlers` needs a mutable connection
+        self.url_schemes.clone().ok_or_else(|| anyhow!("MPD has not listed its URL handlers"))
+    }
+
+    fn send(&mut self, id: &str, command: &PlayerCommand) -> Result<()> {
+        self.check_id(id)?;
+        match command {
+            PlayerCommand::PlayPause if self.state.playback_status() == PlaybackStatus::Playing => {
+                self.command("pause 1")?;
+            }
+            PlayerCommand::PlayPause | PlayerCommand::Play => {
+                self.command("play")?;
+            }
+            PlayerCommand::Pause => {
+                self.command("pause 1")?;
+            }
+            PlayerCommand::Stop => {
+                self.command("stop")?;
+            }
+            PlayerCommand::Next => {
+                self.command("next")?;
+            }
+            PlayerCommand::Previous => {
+                self.command("previous")?;
+            }
+            PlayerCommand::SetPosition { position, .. } => {
+                self.command(&format!("seekcur {:.3}", position.as_secs_f64()))?;
+            }
+            PlayerCommand::SetVolume(volume) => {
+                self.command(&format!("setvol {}", (volume * 100.0).round() as i32))?;
+            }
+            PlayerCommand::OpenUri(uri) => {
+                let added = self.command(&format!("addid {}", quote(uri)))?;
+                let song_id = added
+                    .iter()
+                    .find(|(key, _)| key == "Id")
+                    .map(|(_, id)| id.clone())
+                    .ok_or_else(|| anyhow!("MPD did not add {}", uri))?;
+                self.command(&format!("playid {}", song_id))?;
+            }
+            PlayerCommand::SetRating(rating) => {
+                let file = self.state.song("file").ok_or_else(|| anyhow!("No song to rate"))?.to_string();
+                let rating = (rating.clamp(0.0, 1.0) * 10.0).round() as u8;
+                self.command(&format!("sticker set song {} rating {}", quote(&file), rating))?;
+            }
+            PlayerCommand::SetRate(_) | PlayerCommand::Raise | PlayerCommand::Quit => {
+                bail!("MPD does not support {:?}", command)
+            }
+        }
+        // The idle thread reports the change too, but do not wait for it
+        self.changed.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        net::TcpListener,
+        sync::{mpsc, Mutex},
+    };
+
+    /// Just enough of MPD for the backend: canned `status` and `currentsong`,
+    /// every command recorded, and `idle` answered when `notify` is sent to.
+    struct StubServer {
+        address: String,
+        status: Arc<Mutex<Vec<(String, String)>>>,
+        commands: Arc<Mutex<Vec<String>>>,
+        notify: mpsc::Sender<()>,
+    }
+
+    impl StubServer {
+        fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            let status = Arc::new(Mutex::new(pairs(&[
+                ("volume", "40"),
+                ("state", "play"),
+                ("elapsed", "30.500"),
+                ("duration", "200.000"),
+            ])));
+            let commands = Arc::new(Mutex::new(Vec::new()));
+            let (notify, idle_events) = mpsc::channel::<()>();
+            let idle_events = Arc::new(Mutex::new(idle_events));
+
+            let (server_status, server_commands) = (Arc::clone(&status), Arc::clone(&commands));
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { return };
+                    let (status, commands, idle_events) =
+                        (Arc::clone(&server_status), Arc::clone(&server_commands), Arc::clone(&idle_events));
+                    thread::spawn(move || serve(stream, status, commands, idle_events));
+                }
+            });
+            Self {
+                address,
+                status,
+                commands,
+                notify,
+            }
+        }
+
+        fn backend(&self) -> MpdBackend {
+            MpdBackend::new(MpdConfig {
+                enabled: true,
+                address: self.address.clone(),
+                password: None,
+            })
+        }
+
+        /// Commands other than the ones the backend reads state with
+        fn sent(&self) -> Vec<String> {
+            self.commands
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|c| {
+                    !matches!(c.as_str(), "status" | "currentsong" | "urlhandlers")
+                        && !c.starts_with("idle")
+                        && !c.starts_with("sticker get")
+                })
+                .cloned()
+                .collect()
+        }
+    }
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    fn serve(
+        stream: TcpStream,
+        status: Arc<Mutex<Vec<(String, String)>>>,
+        commands: Arc<Mutex<Vec<String>>>,
+        idle_events: Arc<Mutex<mpsc::Receiver<()>>>,
+    ) {
+        let mut writer = stream.try_clone().unwrap();
+        let mut reader = BufReader::new(stream);
+        writer.write_all(b"OK MPD 0.23.5\n").unwrap();
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap_or(0) > 0 {
+            let command = line.trim().to_string();
+            line.clear();
+            commands.lock().unwrap().push(command.clone());
+
+            let reply = match command.as_str() {
+                "status" => status
+                    .lock()
+                    .unwrap()
+                    .iter()
+                    .map(|(k, v)| format!("{}: {}\n", k, v))
+                    .collect::<String>(),
+                "currentsong" => "file: music/song.flac\nTitle: Stub Song\nArtist: One\nArtist: Two\n\
+                                  Album: Stub Album\nTrack: 3/12\nduration: 200.000\nId: 7\n"
+                    .to_string(),
+                "urlhandlers" => "handler: http://\nhandler: https://\n".to_string(),
+                c if c.starts_with("idle") => {
+                    if idle_events.lock().unwrap().recv().is_err() {
+                        return;
+                    }
+                    "changed: player\n".to_string()
+                }
+                c if c.starts_with("addid") => "Id: 8\n".to_string(),
+                c if c.starts_with("sticker get") => {
+                    writer.write_all(b"ACK [50@0] {sticker} no such sticker\n").unwrap();
+                    continue;
+                }
+                c if c.starts_with("playid 9") => {
+                    writer.write_all(b"ACK [50@0] {playid} No such song\n").unwrap();
+                    continue;
+                }
+                _ => String::new(),
+            };
+            writer.write_all(reply.as_bytes()).unwrap();
+            writer.write_all(b"OK\n").unwrap();
+        }
+    }
+
+    #[test]
+    fn reads_status_and_current_song() {
+        let server = StubServer::start();
+        let mut backend = server.backend();
+
+        let players = backend.find_all().unwrap();
+        assert_eq!(players[0].identity, "MPD");
+        let id = players[0].id.clone();
+
+        let metadata = backend.metadata(&id).unwrap();
+        assert_eq!(metadata.title(), Some("Stub Song"));
+        assert_eq!(metadata.artists(), Some(vec!["One", "Two"]));
+        assert_eq!(metadata.album_name(), Some("Stub Album"));
+        assert_eq!(metadata.track_number(), Some(3));
+        assert_eq!(metadata.length(), Some(Duration::from_secs(200)));
+        assert_eq!(backend.playback_status(&id).unwrap(), PlaybackStatus::Playing);
+        assert!(backend.position(&id).unwrap() >= Duration::from_millis(30_500));
+        assert_eq!(backend.volume(&id).unwrap(), Some(0.4));
+        assert_eq!(backend.supported_uri_schemes(&id).unwrap(), vec!["http", "https"]);
+        assert_eq!(backend.supported_uri_schemes(&id).unwrap(), vec!["http", "https"]);
+        // Asked once, on the backend's own connection
+        let asked = server.commands.lock().unwrap().iter().filter(|c| *c == "urlhandlers").count();
+        assert_eq!(asked, 1);
+        // Unrated, but it can be rated
+        assert_eq!(metadata.get("xesam:userRating"), None);
+        assert!(backend.capabilities(&id).unwrap().can_rate);
+    }
+
+    #[test]
+    fn sends_commands() {
+        let server = StubServer::start();
+        let mut backend = server.backend();
+        let id = backend.find_all().unwrap()[0].id.clone();
+
+        backend.send(&id, &PlayerCommand::PlayPause).unwrap();
+        backend.send(&id, &PlayerCommand::Next).unwrap();
+        backend
+            .send(
+                &id,
+                &PlayerCommand::SetPosition {
+                    track_id: "/org/musicpd/song/7".to_string(),
+                    position: Duration::from_secs(90),
+                },
+            )
+            .unwrap();
+        backend.send(&id, &PlayerCommand::SetVolume(0.55)).unwrap();
+        backend.send(&id, &PlayerCommand::OpenUri("https://example.com/radio".to_string())).unwrap();
+        backend.send(&id, &PlayerCommand::SetRating(0.8)).unwrap();
+        assert!(backend.send(&id, &PlayerCommand::Raise).is_err());
+
+        assert_eq!(
+            server.sent(),
+            vec![
+                "pause 1",
+                "next",
+                "seekcur 90.000",
+                "setvol 55",
+                "addid \"https://example.com/radio\"",
+                "playid 8",
+                "sticker set song \"music/song.flac\" rating 8",
+            ]
+        );
+    }
+
+    #[test]
+    fn rejected_commands_are_errors() {
+        let server = StubServer::start();
+        let mut backend = server.backend();
+        backend.find_all().unwrap();
+
+        let error = backend.command("playid 9").unwrap_err();
+        assert!(error.to_string().contains("No such song"));
+        // The connection is still usable
+        assert!(backend.command("status").is_ok());
+    }
+
+    #[test]
+    fn rereads_state_after_idle_reports_a_change() {
+        let server = StubServer::start();
+        let mut backend = server.backend();
+        let id = backend.find_all().unwrap()[0].id.clone();
+
+        *server.status.lock().unwrap() = pairs(&[("state", "pause"), ("elapsed", "31.000")]);
+        // Without a change from idle the cached state is kept
+        backend.find_all().unwrap();
+        assert_eq!(backend.playback_status(&id).unwrap(), PlaybackStatus::Playing);
+
+        server.notify.send(()).unwrap();
+        for _ in 0..100 {
+            backend.find_all().unwrap();
+            if backend.playback_status(&id).unwrap() == PlaybackStatus::Paused {
+                assert_eq!(backend.position(&id).unwrap(), Duration::from_secs(31));
+                assert_eq!(backend.volume(&id).unwrap(), None);
+                return;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Idle change was not picked up");
+    }
+
+    #[test]
+    fn unreachable_server_is_an_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+        drop(listener);
+
+        let mut backend = MpdBackend::new(MpdConfig {
+            enabled: true,
+            address,
+            password: None,
+        });
+        assert!(backend.find_all().is_err());
+    }
+}
@@ -1,165 +1,1315 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 use serde::{Deserialize, Serialize};
-use mpris::{Player, PlayerFinder, PlaybackStatus};
-use log::{info, error};
+use mpris::{Metadata, MetadataValue, PlaybackStatus};
+use log::{debug, info, error};
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::backend::{Capabilities, PlaybackRate, PlayerBackend, PlayerCommand, PlayerEntry, SessionBusUnavailable};
+use crate::favorites::FavoritesStore;
+use crate::i18n::Message;
+use crate::policy::{PlaybackPolicy, PolicyConfig};
+use crate::resume::{item_key, PositionStore, ResumeConfig};
+use crate::uri;
+
+/// How long a status message stays visible
+const STATUS_MESSAGE_DURATION: Duration = Duration::from_secs(4);
+
+/// Speeds the rate keys step through
+const RATE_STEPS: &[f64] = &[0.5, 0.75, 1.0, 1.25, 1.5, 1.75, 2.0, 2.5, 3.0];
+
+/// Only the top rating counts as a like, so liking never clears other ratings
+const LIKED_RATING: f64 = 1.0;
+
+/// Metadata keys that have a dedicated `TrackInfo` field
+const KNOWN_KEYS: &[&str] = &[
+    "xesam:title",
+    "xesam:artist",
+    "xesam:album",
+    "xesam:albumArtist",
+    "xesam:trackNumber",
+    "xesam:discNumber",
+    "xesam:genre",
+    "xesam:contentCreated",
+    "xesam:composer",
+    "xesam:url",
+    "xesam:userRating",
+    "xesam:useCount",
+    "mpris:trackid",
+    "mpris:length",
+    "mpris:artUrl",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TrackInfo {
     pub title: Option<String>,
-    pub artist: Option<String>,
+    pub artists: Vec<String>,
     pub album: Option<String>,
     pub position: u64,
     pub duration: u64,
     pub is_playing: bool,
     pub cover_url: Option<String>,
+    pub album_artists: Vec<String>,
+    pub track_number: Option<i32>,
+    pub disc_number: Option<i32>,
+    pub genres: Vec<String>,
+    /// Year from `xesam:contentCreated`
+    pub year: Option<String>,
+    pub composers: Vec<String>,
+    pub url: Option<String>,
+    pub track_id: Option<String>,
+    /// `xesam:userRating`, 0.0 to 1.0
+    pub user_rating: Option<f64>,
+    pub play_count: Option<i32>,
+    /// `None` if the player cannot change its speed
+    pub rate: Option<PlaybackRate>,
+    /// 0.0 to 1.0, `None` without volume control
+    pub volume: Option<f64>,
+    /// Remaining metadata entries as (key, formatted value), sorted by key
+    pub extra: Vec<(String, String)>,
 }
 
-impl Default for TrackInfo {
-    fn default() -> Self {
+impl TrackInfo {
+    fn from_player_state(status: PlaybackStatus, metadata: &Metadata, position: Duration) -> Self {
+        let strings = |key: &str| -> Vec<String> {
+            match metadata.get(key) {
+                Some(MetadataValue::String(s)) => vec![s.clone()],
+                Some(value) => value
+                    .as_str_array()
+                    .map(|values| values.into_iter().map(|s| s.to_string()).collect())
+                    .unwrap_or_default(),
+                None => Vec::new(),
+            }
+        };
+        let number = |key: &str| metadata.get(key).and_then(value_as_i32);
+
+        let mut extra: Vec<(String, String)> = metadata
+            .iter()
+            .filter(|(key, _)| !KNOWN_KEYS.contains(key))
+            .map(|(key, value)| (key.to_string(), format_value(value)))
+            .collect();
+        extra.sort();
+
         Self {
-            title: None,
-            artist: None,
-            album: None,
-            position: 0,
-            duration: 0,
-            is_playing: false,
-            cover_url: None,
+            title: metadata.title().map(|s| s.to_string()),
+            artists: strings("xesam:artist"),
+            album: metadata.album_name().map(|s| s.to_string()),
+            position: position.as_secs(),
+            duration: metadata.length().map(|d| d.as_secs()).unwrap_or(0),
+            is_playing: status == PlaybackStatus::Playing,
+            cover_url: metadata.art_url().map(|s| s.to_string()),
+            album_artists: strings("xesam:albumArtist"),
+            track_number: number("xesam:trackNumber"),
+            disc_number: number("xesam:discNumber"),
+            genres: strings("xesam:genre"),
+            year: metadata
+                .get("xesam:contentCreated")
+                .and_then(MetadataValue::as_str)
+                .and_then(|date| date.get(..4))
+                .map(|year| year.to_string()),
+            composers: strings("xesam:composer"),
+            url: metadata.url().map(|s| s.to_string()),
+            track_id: metadata
+                .get("mpris:trackid")
+                .and_then(MetadataValue::as_str)
+                .map(|s| s.to_string()),
+            user_rating: metadata.get("xesam:userRating").and_then(MetadataValue::as_f64),
+            play_count: number("xesam:useCount"),
+            rate: None,
+            volume: None,
+            extra,
+        }
+    }
+
+    /// Speed factor, 1.0 for players that cannot change it
+    pub fn rate_factor(&self) -> f64 {
+        self.rate.map_or(1.0, |rate| rate.current)
+    }
+
+    /// A stream without a length. Tracks whose length has not been sent yet
+    /// look the same, so only those with something to play count.
+    pub fn is_live(&self) -> bool {
+        self.duration == 0 && (self.title.is_some() || self.url.is_some())
+    }
+
+    /// Wall-clock seconds until the end at the current speed; a rate of 0
+    /// means paused, which counts as normal speed
+    pub fn remaining(&self) -> u64 {
+        let remaining = self.duration.saturating_sub(self.position);
+        let rate = self.rate_factor();
+        if rate <= 0.0 {
+            return remaining;
         }
+        (remaining as f64 / rate).round() as u64
+    }
+
+    /// All artists joined for display
+    pub fn artist_display(&self) -> Option<String> {
+        (!self.artists.is_empty()).then(|| self.artists.join(", "))
     }
 }
 
+/// Players disagree on integer widths, so accept any of them.
+fn value_as_i32(value: &MetadataValue) -> Option<i32> {
+    match value {
+        MetadataValue::I16(n) => Some(*n as i32),
+        MetadataValue::I32(n) => Some(*n),
+        MetadataValue::I64(n) => i32::try_from(*n).ok(),
+        MetadataValue::U8(n) => Some(*n as i32),
+        MetadataValue::U16(n) => Some(*n as i32),
+        MetadataValue::U32(n) => i32::try_from(*n).ok(),
+        MetadataValue::U64(n) => i32::try_from(*n).ok(),
+        _ => None,
+    }
+}
+
+fn format_value(value: &MetadataValue) -> String {
+    match value {
+        MetadataValue::String(s) => s.clone(),
+        MetadataValue::I16(n) => n.to_string(),
+        MetadataValue::I32(n) => n.to_string(),
+        MetadataValue::I64(n) => n.to_string(),
+        MetadataValue::U8(n) => n.to_string(),
+        MetadataValue::U16(n) => n.to_string(),
+        MetadataValue::U32(n) => n.to_string(),
+        MetadataValue::U64(n) => n.to_string(),
+        MetadataValue::F64(n) => n.to_string(),
+        MetadataValue::Bool(b) => b.to_string(),
+        MetadataValue::Array(values) => values.iter().map(format_value).collect::<Vec<_>>().join(", "),
+        MetadataValue::Map(map) => {
+            let mut entries: Vec<String> = map
+                .iter()
+                .map(|(key, value)| format!("{}={}", key, format_value(value)))
+                .collect();
+            entries.sort();
+            format!("{{{}}}", entries.join(", "))
+        }
+        MetadataValue::Unsupported => "?".to_string(),
+    }
+}
+
+/// Message key for how a command is described in status messages
+fn command_name(command: &PlayerCommand) -> &'static str {
+    match command {
+        PlayerCommand::PlayPause => "command.play_pause",
+        PlayerCommand::Play => "command.play",
+        PlayerCommand::Pause => "command.pause",
+        PlayerCommand::Stop => "command.stop",
+        PlayerCommand::Next => "command.next",
+        PlayerCommand::Previous => "command.previous",
+        PlayerCommand::SetPosition { .. } => "command.seek",
+        PlayerCommand::SetRate(_) => "command.rate",
+        PlayerCommand::Raise => "command.raise",
+        PlayerCommand::Quit => "command.quit",
+        PlayerCommand::OpenUri(_) => "command.open_uri",
+        PlayerCommand::SetVolume(_) => "command.volume",
+        PlayerCommand::SetRating(_) => "command.rating",
+    }
+}
+
+/// A remembered position for an item a player just started
+#[derive(Debug, Clone)]
+pub struct ResumeOffer {
+    pub player_id: String,
+    pub player_name: String,
+    pub title: Option<String>,
+    pub track_id: String,
+    /// Seconds into the item
+    pub position: u64,
+}
+
+/// Last known state of one player
+#[derive(Debug, Clone)]
+pub struct PlayerState {
+    pub entry: PlayerEntry,
+    pub track: TrackInfo,
+    pub capabilities: Capabilities,
+}
+
 pub struct MusicPlayer {
-    current_track: TrackInfo,
-    player_finder: PlayerFinder,
-    current_player: Option<Player>,
-    last_update: std::time::Instant,
+    backend: Box<dyn PlayerBackend>,
+    players: Vec<PlayerState>,
+    current_player: Option<usize>,
+    no_track: TrackInfo,
+    policy: PlaybackPolicy,
+    resume_config: ResumeConfig,
+    positions: PositionStore,
+    /// Remembered item each player showed on the last update
+    last_items: HashMap<String, Option<String>>,
+    resume_offer: Option<ResumeOffer>,
+    /// Players whose item has a saved position that has not been offered
+    /// yet, because of another offer or a missing track id. It is kept
+    /// until offered or played past.
+    unoffered: HashMap<String, String>,
+    favorites: FavoritesStore,
+    status_message: Option<(Message, Instant)>,
+    bus_error: Option<String>,
+    last_update: Option<std::time::Instant>,
 }
 
 impl MusicPlayer {
-    pub fn new() -> Self {
+    pub fn new(backend: Box<dyn PlayerBackend>) -> Self {
         Self {
-            current_track: TrackInfo::default(),
-            player_finder: PlayerFinder::new().unwrap_or_else(|_| {
-                // Create a dummy finder if connection fails
-                PlayerFinder::new().unwrap()
-            }),
+            backend,
+            players: Vec::new(),
             current_player: None,
-            last_update: std::time::Instant::now(),
+            no_track: TrackInfo::default(),
+            policy: PlaybackPolicy::default(),
+            resume_config: ResumeConfig {
+                enabled: false,
+                ..ResumeConfig::default()
+            },
+            positions: PositionStore::default(),
+            last_items: HashMap::new(),
+            resume_offer: None,
+            unoffered: HashMap::new(),
+            favorites: FavoritesStore::default(),
+            status_message: None,
+            bus_error: None,
+            last_update: None,
         }
     }
 
     pub fn update(&mut self) {
         // Only update every 2 seconds to avoid excessive D-Bus calls
-        if self.last_update.elapsed() < std::time::Duration::from_secs(2) {
+        if self.last_update.is_some_and(|t| t.elapsed() < Duration::from_secs(2)) {
             return;
         }
 
-        // Try to find and connect to MPRIS players
-        if let Err(e) = self.update_from_mpris() {
-            error!("Failed to update from MPRIS: {}", e);
+        // Try to find and connect to players; a missing bus is only reported once
+        let bus_was_down = self.bus_error.is_some();
+        if let Err(e) = self.update_from_backend() {
+            if !(bus_was_down && e.is::<SessionBusUnavailable>()) {
+                error!("Failed to update from backend: {}", e);
+            }
         }
 
-        self.last_update = std::time::Instant::now();
+        self.last_update = Some(std::time::Instant::now());
+    }
+
+    /// Refresh on the next `update` instead of waiting for the interval
+    pub fn refresh_soon(&mut self) {
+        self.last_update = None;
     }
 
-    fn update_from_mpris(&mut self) -> Result<()> {
-        // Find all available MPRIS players
-        let players = self.player_finder.find_all()?;
-        
-        // If no players available, clear current player
-        if players.is_empty() {
-            self.current_player = None;
-            self.current_track = TrackInfo::default();
-            return Ok(());
+    fn update_from_backend(&mut self) -> Result<()> {
+        // Find all available players
+        let entries = match self.backend.find_all() {
+            Ok(entries) => {
+                if self.bus_error.take().is_some() {
+                    info!("D-Bus session bus is available again");
+                }
+                entries
+            }
+            Err(e) => {
+                if let Some(bus_error) = e.downcast_ref::<SessionBusUnavailable>() {
+                    // Without a bus there is nothing to control, forget the stale players
+                    self.bus_error = Some(bus_error.reason.clone());
+                    self.players.clear();
+                    self.current_player = None;
+                }
+                return Err(e);
+            }
+        };
+
+        let current_id = self.get_current_player().map(|p| p.id.clone());
+
+        // Update track info for every player; one failing player keeps its last state
+        let mut players = Vec::with_capacity(entries.len());
+        for entry in entries {
+            let previous = self.players.iter().find(|p| p.entry.id == entry.id);
+            let track = match self.get_track_info_from_player(&entry.id) {
+                Ok(track) => track,
+                Err(e) => {
+                    error!("Failed to read state of {}: {}", entry.identity, e);
+                    previous.map(|p| p.track.clone()).unwrap_or_default()
+                }
+            };
+            let capabilities = self.backend.capabilities(&entry.id).unwrap_or_else(|e| {
+                debug!("Failed to read capabilities of {}: {}", entry.identity, e);
+                previous.map(|p| p.capabilities).unwrap_or_default()
+            });
+            players.push(PlayerState { entry, track, capabilities });
         }
-        
-        // If current player is no longer available, switch to first available player
-        if let Some(ref current_player) = self.current_player {
-            if !players.iter().any(|p| p.identity() == current_player.identity()) {
-                self.current_player = Some(players.into_iter().next().unwrap());
+        self.players = players;
+
+        // Keep the current player while it is available, otherwise switch to the first one
+        self.current_player = current_id
+            .as_ref()
+            .and_then(|id| self.players.iter().position(|p| &p.entry.id == id))
+            .or(if self.players.is_empty() { None } else { Some(0) });
+        if let Some(player) = self.get_current_player() {
+            if current_id.as_ref() != Some(&player.id) {
+                info!("Switching to player {}", player.identity);
             }
-        } else {
-            // No current player, select the first available one
-            self.current_player = Some(players.into_iter().next().unwrap());
-        }
-        
-        // Update track info for current player
-        if self.current_player.is_some() {
-            let player = self.current_player.take().unwrap();
-            let track_info = self.get_track_info_from_player(&player)?;
-            self.current_track = track_info;
-            self.current_player = Some(player);
-        }
-        
+        }
+
+        for (id, command) in self.policy.apply(&self.players) {
+            self.send_quietly(&id, command);
+        }
+
+        self.remember_positions();
+
         Ok(())
     }
 
-    fn get_track_info_from_player(&self, player: &Player) -> Result<TrackInfo> {
-        // Get playback status
-        let playback_status = player.get_playback_status()?;
-        
-        // Get metadata
-        let metadata = player.get_metadata()?;
-        
-        // Get position
-        let position = player.get_position().unwrap_or(std::time::Duration::from_secs(0));
-        
-        // Create track info
-        Ok(TrackInfo {
-            title: metadata.title().map(|s| s.to_string()),
-            artist: metadata.artists().and_then(|artists| artists.first().map(|s| s.to_string())),
-            album: metadata.album_name().map(|s| s.to_string()),
-            position: (position.as_micros() / 1_000_000) as u64, // Convert from microseconds to seconds
-            duration: metadata.length().map(|d| (d.as_micros() / 1_000_000) as u64).unwrap_or(0),
-            is_playing: playback_status == PlaybackStatus::Playing,
-            cover_url: metadata.art_url().map(|s| s.to_string()),
-        })
+    /// Save positions of long items and offer to resume ones seen before
+    fn remember_positions(&mut self) {
+        if !self.resume_config.enabled {
+            return;
+        }
+        let players = &self.players;
+        self.last_items.retain(|id, _| players.iter().any(|p| &p.entry.id == id));
+        self.unoffered.retain(|id, _| players.iter().any(|p| &p.entry.id == id));
+
+        let mut offers = Vec::new();
+        for player in &self.players {
+            let track = &player.track;
+            let id = &player.entry.id;
+            let key = item_key(track).filter(|_| self.resume_config.applies_to(track));
+            let previous = self.last_items.insert(id.clone(), key.clone());
+            let Some(key) = key else {
+                self.unoffered.remove(id);
+                continue;
+            };
+
+            let is_new = previous.flatten().as_deref() != Some(key.as_str());
+            if is_new {
+                if self.resume_offer.as_ref().is_some_and(|o| &o.player_id == id) {
+                    self.resume_offer = None;
+                }
+                self.unoffered.remove(id);
+                if let Some(saved) = self.positions.get(&key) {
+                    info!("{} was last played at {}s", key, saved.position);
+                    self.unoffered.insert(id.clone(), key.clone());
+                }
+            }
+
+            if self.unoffered.get(id) == Some(&key) {
+                // Only worth offering if the saved position is noticeably further along
+                match self.positions.get(&key).filter(|saved| saved.position > track.position + 10) {
+                    None => {
+                        self.unoffered.remove(id);
+                    }
+                    // Only one offer is shown at a time, a pending one for another player goes first
+                    Some(saved) => {
+                        let can_offer = self.resume_config.auto_resume
+                            || (self.resume_offer.is_none() && offers.is_empty());
+                        match &track.track_id {
+                            Some(track_id) if can_offer => {
+                                offers.push(ResumeOffer {
+                                    player_id: id.clone(),
+                                    player_name: player.entry.identity.clone(),
+                                    title: track.title.clone(),
+                                    track_id: track_id.clone(),
+                                    position: saved.position,
+                                });
+                                self.unoffered.remove(id);
+                            }
+                            Some(_) => {}
+                            None if is_new => {
+                                info!("Not offering to resume {}, {} has no track id", key, player.entry.identity);
+                            }
+                            None => {}
+                        }
+                        continue;
+                    }
+                }
+            }
+            // Keep the saved position until the offer is answered
+            let offered = self.resume_offer.as_ref().is_some_and(|o| o.player_id == player.entry.id);
+            if !offered && (track.is_playing || is_new) {
+                self.positions.record(&key, track);
+            }
+        }
+
+        for offer in offers {
+            if self.resume_config.auto_resume {
+                self.resume(offer, false);
+            } else {
+                self.resume_offer = Some(offer);
+            }
+        }
+        self.positions.flush_if_due();
+    }
+
+    /// Seek to `offer`; `asked` is whether the user accepted it or it resumed on its own
+    fn resume(&mut self, offer: ResumeOffer, asked: bool) {
+        let command = PlayerCommand::SetPosition {
+            track_id: offer.track_id,
+            position: Duration::from_secs(offer.position),
+        };
+        self.deliver(&offer.player_id, command, asked);
+    }
+
+    fn get_track_info_from_player(&self, id: &str) -> Result<TrackInfo> {
+        let playback_status = self.backend.playback_status(id)?;
+        let metadata = self.backend.metadata(id)?;
+        let position = self.backend.position(id).unwrap_or(Duration::from_secs(0));
+
+        let mut track = TrackInfo::from_player_state(playback_status, &metadata, position);
+        track.rate = self.backend.rate(id).unwrap_or_else(|e| {
+            debug!("Failed to read playback rate of {}: {}", id, e);
+            None
+        });
+        track.volume = self.backend.volume(id).unwrap_or_else(|e| {
+            debug!("Failed to read volume of {}: {}", id, e);
+            None
+        });
+        Ok(track)
     }
 
     pub fn get_current_track(&self) -> &TrackInfo {
-        &self.current_track
+        self.current_player
+            .map(|index| &self.players[index].track)
+            .unwrap_or(&self.no_track)
+    }
+
+    pub fn get_current_player(&self) -> Option<&PlayerEntry> {
+        self.current_player.map(|index| &self.players[index].entry)
+    }
+
+    /// Every known player, in discovery order
+    pub fn get_players(&self) -> &[PlayerState] {
+        &self.players
+    }
+
+    /// Make the player with `id` the current one
+    pub fn select_player(&mut self, id: &str) {
+        if let Some(index) = self.players.iter().position(|p| p.entry.id == id) {
+            if self.current_player != Some(index) {
+                info!("Switching to player {}", self.players[index].entry.identity);
+                self.current_player = Some(index);
+            }
+        }
+    }
+
+    /// Make the first player whose name or bus name contains `name` current
+    pub fn select_player_by_name(&mut self, name: &str) -> bool {
+        let name = name.to_lowercase();
+        let found = self.players.iter().find(|p| {
+            p.entry.identity.to_lowercase().contains(&name) || p.entry.id.to_lowercase().contains(&name)
+        });
+        match found.map(|p| p.entry.id.clone()) {
+            Some(id) => {
+                self.select_player(&id);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Make the player after the current one the current one, wrapping around
+    pub fn select_next_player(&mut self) {
+        if self.players.is_empty() {
+            return;
+        }
+        let next = self.current_player.map(|index| (index + 1) % self.players.len()).unwrap_or(0);
+        let id = self.players[next].entry.id.clone();
+        self.select_player(&id);
+    }
+
+    pub fn set_policy(&mut self, config: PolicyConfig) {
+        self.policy = PlaybackPolicy::new(config);
+    }
+
+    pub fn is_exclusive(&self) -> bool {
+        self.policy.is_enabled()
+    }
+
+    /// Turn exclusive playback on or off
+    pub fn toggle_exclusive(&mut self) {
+        let enabled = !self.policy.is_enabled();
+        self.policy.set_enabled(enabled);
+        info!("Exclusive playback {}", if enabled { "enabled" } else { "disabled" });
+    }
+
+    /// Remember positions of long items in `positions`
+    pub fn set_resume(&mut self, config: ResumeConfig, positions: PositionStore) {
+        self.resume_config = config;
+        self.positions = positions;
+    }
+
+    /// A saved position waiting for the user to resume it
+    pub fn resume_offer(&self) -> Option<&ResumeOffer> {
+        self.resume_offer.as_ref()
+    }
+
+    pub fn accept_resume(&mut self) {
+        if let Some(offer) = self.resume_offer.take() {
+            self.resume(offer, true);
+        }
     }
-    
-    pub fn get_current_player(&self) -> Option<&Player> {
-        self.current_player.as_ref()
+
+    pub fn dismiss_resume(&mut self) {
+        self.resume_offer = None;
     }
-    
-    pub fn is_connected(&self) -> bool {
-        self.current_player.is_some()
+
+    /// Write remembered positions to disk, e.g. before quitting
+    pub fn save_positions(&mut self) {
+        self.positions.flush();
     }
-    
-    pub fn get_available_players(&self) -> Result<Vec<String>> {
-        let players = self.player_finder.find_all()?;
-        Ok(players.into_iter().map(|p| p.identity().to_string()).collect())
+
+    /// Keep liked tracks of players that cannot rate in `favorites`
+    pub fn set_favorites(&mut self, favorites: FavoritesStore) {
+        self.favorites = favorites;
+    }
+
+    pub fn favorites(&self) -> &FavoritesStore {
+        &self.favorites
+    }
+
+    /// Whether the current track is rated by its player or in the favorites
+    pub fn is_liked(&self) -> bool {
+        let track = self.get_current_track();
+        track.user_rating.is_some_and(|rating| rating >= LIKED_RATING) || self.favorites.contains(track)
+    }
+
+    /// Like the current track, or unlike it. Players that can store a rating
+    /// get the highest one; for the others the track goes into the favorites.
+    pub fn toggle_like(&mut self) {
+        let Some(index) = self.current_player else {
+            return;
+        };
+        let player = &self.players[index];
+        if player.track.title.is_none() {
+            self.set_status_message(Message::new("status.nothing_to_like"));
+            return;
+        }
+
+        if player.capabilities.can_rate {
+            // Lower ratings are raised to a like; only a like is taken back to none. A like
+            // in the favorites, from a player that could not rate, leaves the rating alone.
+            let rated = player.track.user_rating.is_some_and(|rating| rating >= LIKED_RATING);
+            let rating = if rated { 0.0 } else { LIKED_RATING };
+            if self.send_command(PlayerCommand::SetRating(rating)) {
+                // Show the new rating right away instead of after the next update
+                self.players[index].track.user_rating = Some(rating);
+            }
+        } else {
+            let liked = self.favorites.toggle(&player.track, &player.entry.identity);
+            let title = player.track.title.clone().unwrap_or_default();
+            let message = Message::new(if liked { "status.liked" } else { "status.unliked" }).arg("title", title);
+            info!("{}", message);
+            self.set_status_message(message);
+        }
+    }
+
+    /// Capabilities of the current player; none without a player
+    pub fn get_current_capabilities(&self) -> Capabilities {
+        match self.current_player {
+            Some(index) => self.players[index].capabilities,
+            None => Capabilities {
+                can_control: false,
+                can_raise: false,
+                can_quit: false,
+                ..Capabilities::default()
+            },
+        }
+    }
+
+    fn set_status_message(&mut self, message: Message) {
+        self.status_message = Some((message, Instant::now()));
+    }
+
+    /// A short message about the last rejected command, shown for a few seconds
+    pub fn status_message(&self) -> Option<&Message> {
+        self.status_message
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < STATUS_MESSAGE_DURATION)
+            .map(|(message, _)| message)
+    }
+
+    /// Why the session bus could not be reached, if it currently can't be.
+    pub fn bus_error(&self) -> Option<&str> {
+        self.bus_error.as_deref()
+    }
+
+    /// Whether the player took the command; if not, the status line says why
+    fn send_to(&mut self, id: &str, command: PlayerCommand) -> bool {
+        self.deliver(id, command, true)
+    }
+
+    /// For commands the user did not give, like the policy's pauses, which
+    /// must not take over the status line
+    fn send_quietly(&mut self, id: &str, command: PlayerCommand) -> bool {
+        self.deliver(id, command, false)
+    }
+
+    fn deliver(&mut self, id: &str, command: PlayerCommand, report: bool) -> bool {
+        let Some(player) = self.players.iter().find(|p| p.entry.id == id) else {
+            return false;
+        };
+        if !player.capabilities.allows(&command) {
+            let message = Message::new("status.unsupported")
+                .arg("player", player.entry.identity.clone())
+                .arg_key("command", command_name(&command));
+            info!("{}", message);
+            if report {
+                self.set_status_message(message);
+            }
+            return false;
+        }
+        let sent = match self.backend.send(id, &command) {
+            Ok(()) => {
+                info!("Sent {:?} command to {}", command, player.entry.identity);
+                true
+            }
+            Err(e) => {
+                error!("Failed to send {:?} command: {}", command, e);
+                if report {
+                    let message = Message::new("status.rejected")
+                        .arg("player", player.entry.identity.clone())
+                        .arg_key("command", command_name(&command));
+                    self.set_status_message(message);
+                }
+                false
+            }
+        };
+        self.refresh_soon();
+        sent
+    }
+
+    /// Whether the current player took the command
+    fn send_command(&mut self, command: PlayerCommand) -> bool {
+        match self.get_current_player().map(|p| p.id.clone()) {
+            Some(id) => self.send_to(&id, command),
+            None => false,
+        }
     }
 
     pub fn toggle_play_pause(&mut self) {
-        if let Some(ref player) = self.current_player {
-            if let Err(e) = player.play_pause() {
-                error!("Failed to send PlayPause command: {}", e);
-            } else {
-                info!("Sent PlayPause command to {}", player.identity());
+        self.send_command(PlayerCommand::PlayPause);
+    }
+
+    pub fn toggle_play_pause_for(&mut self, id: &str) {
+        self.send_to(id, PlayerCommand::PlayPause);
+    }
+
+    /// Step the speed of the current player up or down through `RATE_STEPS`,
+    /// staying within the player's limits
+    pub fn step_rate(&mut self, faster: bool) {
+        let Some(rate) = self.get_current_track().rate else {
+            return;
+        };
+        let steps = RATE_STEPS
+            .iter()
+            .copied()
+            .filter(|step| (rate.minimum..=rate.maximum).contains(step));
+        let next = if faster {
+            steps.filter(|step| *step > rate.current + 0.01).reduce(f64::min)
+        } else {
+            steps.filter(|step| *step < rate.current - 0.01).reduce(f64::max)
+        };
+        if let Some(next) = next {
+            self.set_rate(next);
+        }
+    }
+
+    /// Back to normal speed
+    pub fn reset_rate(&mut self) {
+        if self.get_current_track().rate.is_some_and(|rate| rate.current != 1.0) {
+            self.set_rate(1.0);
+        }
+    }
+
+    fn set_rate(&mut self, rate: f64) {
+        if !self.send_command(PlayerCommand::SetRate(rate)) {
+            return;
+        }
+        // Show the new speed right away instead of after the next update
+        if let Some(index) = self.current_player {
+            if let Some(current) = self.players[index].track.rate.as_mut() {
+                current.current = rate;
             }
         }
     }
 
-    pub fn next(&mut self) {
-        if let Some(ref player) = self.current_player {
-            if let Err(e) = player.next() {
-                error!("Failed to send Next command: {}", e);
-            } else {
-                info!("Sent Next command to {}", player.identity());
+    pub fn stop(&mut self) {
+        self.send_command(PlayerCommand::Stop);
+    }
+
+    /// Bring the current player's window to the front
+    pub fn raise(&mut self) {
+        self.send_command(PlayerCommand::Raise);
+    }
+
+    /// Close the player `id`
+    pub fn quit_player(&mut self, id: &str) {
+        self.send_to(id, PlayerCommand::Quit);
+    }
+
+    /// Send a URL or local path to the current player after checking the
+    /// player supports its scheme
+    pub fn open_uri(&mut self, input: &str) -> Result<()> {
+        let result = self.try_open_uri(input);
+        match &result {
+            Ok(uri) => self.set_status_message(Message::new("status.opening").arg("uri", uri)),
+            Err(e) => {
+                let message = e
+                    .downcast_ref::<Message>()
+                    .cloned()
+                    .unwrap_or_else(|| Message::new("open.failed").arg("error", format!("{:#}", e)));
+                self.set_status_message(message);
             }
         }
+        result.map(|_| ())
+    }
+
+    fn try_open_uri(&mut self, input: &str) -> Result<String> {
+        let Some(player) = self.get_current_player().cloned() else {
+            bail!(Message::new("open.no_player"));
+        };
+        let uri = uri::to_uri(input)?;
+        let command = PlayerCommand::OpenUri(uri.clone());
+        if !self.get_current_capabilities().allows(&command) {
+            bail!(Message::new("status.unsupported")
+                .arg("player", player.identity)
+                .arg_key("command", command_name(&command)));
+        }
+        let schemes = self.backend.supported_uri_schemes(&player.id)?;
+        uri::check_scheme(&uri, &schemes)?;
+        self.backend.send(&player.id, &command)?;
+        info!("Opened {} in {}", uri, player.identity);
+        self.refresh_soon();
+        Ok(uri)
+    }
+
+    /// Change the volume of the current player by `delta` (e.g. 0.05 for 5%)
+    pub fn step_volume(&mut self, delta: f64) {
+        let Some(volume) = self.get_current_track().volume else {
+            self.set_status_message(Message::new("status.no_volume"));
+            return;
+        };
+        let volume = ((volume + delta) * 100.0).round().clamp(0.0, 100.0) / 100.0;
+        if !self.send_command(PlayerCommand::SetVolume(volume)) {
+            return;
+        }
+        if let Some(index) = self.current_player {
+            self.players[index].track.volume = Some(volume);
+        }
+    }
+
+    pub fn next(&mut self) {
+        self.send_command(PlayerCommand::Next);
     }
 
     pub fn previous(&mut self) {
-        if let Some(ref player) = self.current_player {
-            if let Err(e) = player.previous() {
-                error!("Failed to send Previous command: {}", e);
-            } else {
-                info!("Sent Previous command to {}", player.identity());
-            }
+        self.send_command(PlayerCommand::Previous);
+    }
+
+    /// Pause every playing player except the current one
+    pub fn pause_all_others(&mut self) {
+        let current = self.get_current_player().map(|p| p.id.clone());
+        let others: Vec<String> = self
+            .players
+            .iter()
+            .filter(|p| p.track.is_playing && Some(&p.entry.id) != current.as_ref())
+            .map(|p| p.entry.id.clone())
+            .collect();
+        for id in others {
+            self.send_to(&id, PlayerCommand::Pause);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::fake::{FakeBackend, FakePlayer};
+    use mpris::MetadataValue;
+
+    fn player_with(backend: &FakeBackend) -> MusicPlayer {
+        MusicPlayer::new(Box::new(backend.clone()))
+    }
+
+    #[test]
+    fn no_players_means_no_current_player() {
+        let backend = FakeBackend::default();
+        let mut player = player_with(&backend);
+        player.update();
+
+        assert!(player.get_current_player().is_none());
+        assert!(player.get_current_track().title.is_none());
+    }
+
+    #[test]
+    fn reads_track_info_from_first_player() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("org.mpris.MediaPlayer2.a", "A")
+            .status(PlaybackStatus::Playing)
+            .position(Duration::from_secs(42))
+            .with("xesam:title", "Song")
+            .with("xesam:artist", vec![MetadataValue::from("First"), MetadataValue::from("Second")])
+            .with("xesam:album", "Album")
+            .with("mpris:length", 180_000_000i64)
+            .with("mpris:artUrl", "file:///tmp/cover.png")]);
+        let mut player = player_with(&backend);
+        player.update();
+
+        let track = player.get_current_track();
+        assert_eq!(player.get_current_player().unwrap().identity, "A");
+        assert_eq!(track.title.as_deref(), Some("Song"));
+        assert_eq!(track.artists, vec!["First", "Second"]);
+        assert_eq!(track.artist_display().as_deref(), Some("First, Second"));
+        assert_eq!(track.album.as_deref(), Some("Album"));
+        assert_eq!(track.position, 42);
+        assert_eq!(track.duration, 180);
+        assert!(track.is_playing);
+        assert_eq!(track.cover_url.as_deref(), Some("file:///tmp/cover.png"));
+    }
+
+    #[test]
+    fn reads_all_metadata_fields() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A")
+            .with("xesam:albumArtist", vec![MetadataValue::from("Various")])
+            .with("xesam:trackNumber", 3)
+            .with("xesam:discNumber", 2u32)
+            .with("xesam:genre", vec![MetadataValue::from("Jazz"), MetadataValue::from("Fusion")])
+            .with("xesam:contentCreated", "1999-05-01")
+            .with("xesam:composer", vec![MetadataValue::from("Composer")])
+            .with("xesam:url", "file:///music/track.flac")
+            .with("xesam:userRating", 0.8)
+            .with("xesam:useCount", 12)
+            .with("xesam:lyricist", vec![MetadataValue::from("Writer")])
+            .with("xesam:bpm", 120)]);
+        let mut player = player_with(&backend);
+        player.update();
+
+        let track = player.get_current_track();
+        assert_eq!(track.album_artists, vec!["Various"]);
+        assert_eq!(track.track_number, Some(3));
+        assert_eq!(track.disc_number, Some(2));
+        assert_eq!(track.genres, vec!["Jazz", "Fusion"]);
+        assert_eq!(track.year.as_deref(), Some("1999"));
+        assert_eq!(track.composers, vec!["Composer"]);
+        assert_eq!(track.url.as_deref(), Some("file:///music/track.flac"));
+        assert_eq!(track.track_id.as_deref(), Some("/A/track/0"));
+        assert_eq!(track.user_rating, Some(0.8));
+        assert_eq!(track.play_count, Some(12));
+        assert_eq!(
+            track.extra,
+            vec![
+                ("xesam:bpm".to_string(), "120".to_string()),
+                ("xesam:lyricist".to_string(), "Writer".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn keeps_current_player_while_available() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A")]);
+        let mut player = player_with(&backend);
+        player.update_from_backend().unwrap();
+
+        backend.set_players(vec![FakePlayer::new("b", "B"), FakePlayer::new("a", "A")]);
+        player.update_from_backend().unwrap();
+        assert_eq!(player.get_current_player().unwrap().id, "a");
+    }
+
+    #[test]
+    fn switches_when_current_player_disappears() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A"), FakePlayer::new("b", "B")]);
+        let mut player = player_with(&backend);
+        player.update_from_backend().unwrap();
+        assert_eq!(player.get_current_player().unwrap().id, "a");
+
+        backend.set_players(vec![FakePlayer::new("b", "B")]);
+        player.update_from_backend().unwrap();
+        assert_eq!(player.get_current_player().unwrap().id, "b");
+
+        backend.set_players(vec![]);
+        player.update_from_backend().unwrap();
+        assert!(player.get_current_player().is_none());
+    }
+
+    #[test]
+    fn selected_player_stays_current() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A"), FakePlayer::new("b", "B")]);
+        let mut player = player_with(&backend);
+        player.update_from_backend().unwrap();
+
+        player.select_player("b");
+        player.update_from_backend().unwrap();
+        assert_eq!(player.get_current_player().unwrap().id, "b");
+        assert_eq!(player.get_players().len(), 2);
+
+        // Unknown ids are ignored
+        player.select_player("c");
+        assert_eq!(player.get_current_player().unwrap().id, "b");
+
+        player.select_next_player();
+        assert_eq!(player.get_current_player().unwrap().id, "a");
+    }
+
+    #[test]
+    fn pause_all_others_only_pauses_playing_players() {
+        let backend = FakeBackend::with_players(vec![
+            FakePlayer::new("a", "A").status(PlaybackStatus::Playing),
+            FakePlayer::new("b", "B").status(PlaybackStatus::Playing),
+            FakePlayer::new("c", "C").status(PlaybackStatus::Paused),
+        ]);
+        let mut player = player_with(&backend);
+        player.update_from_backend().unwrap();
+
+        player.pause_all_others();
+        assert_eq!(backend.sent(), vec![("b".to_string(), PlayerCommand::Pause)]);
+
+        player.toggle_play_pause_for("c");
+        assert_eq!(backend.sent()[1], ("c".to_string(), PlayerCommand::PlayPause));
+    }
+
+    #[test]
+    fn exclusive_policy_pauses_other_players() {
+        let backend = FakeBackend::with_players(vec![
+            FakePlayer::new("a", "A").status(PlaybackStatus::Playing),
+            FakePlayer::new("b", "B"),
+        ]);
+        let mut player = player_with(&backend);
+        player.set_policy(PolicyConfig {
+            exclusive: true,
+            ..PolicyConfig::default()
+        });
+        player.update_from_backend().unwrap();
+        assert!(backend.sent().is_empty());
+
+        backend.set_players(vec![
+            FakePlayer::new("a", "A").status(PlaybackStatus::Playing),
+            FakePlayer::new("b", "B").status(PlaybackStatus::Playing),
+        ]);
+        player.update_from_backend().unwrap();
+        assert_eq!(backend.sent(), vec![("a".to_string(), PlayerCommand::Pause)]);
+        assert!(player.status_message().is_none());
+
+        // Pauses the user did not ask for do not report failing either
+        backend.set_players(vec![FakePlayer::new("a", "A").status(PlaybackStatus::Playing), FakePlayer::new("b", "B")]);
+        player.update_from_backend().unwrap();
+        backend.state.borrow_mut().fail_commands = true;
+        backend.set_players(vec![
+            FakePlayer::new("a", "A").status(PlaybackStatus::Playing),
+            FakePlayer::new("b", "B").status(PlaybackStatus::Playing),
+        ]);
+        player.update_from_backend().unwrap();
+        assert!(player.status_message().is_none());
+    }
+
+    #[test]
+    fn commands_go_to_current_player() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A")]);
+        let mut player = player_with(&backend);
+
+        // Nothing is sent before a player has been discovered
+        player.toggle_play_pause();
+        assert!(backend.sent().is_empty());
+
+        player.update();
+        player.toggle_play_pause();
+        player.next();
+        player.previous();
+        assert_eq!(
+            backend.sent(),
+            vec![
+                ("a".to_string(), PlayerCommand::PlayPause),
+                ("a".to_string(), PlayerCommand::Next),
+                ("a".to_string(), PlayerCommand::Previous),
+            ]
+        );
+    }
+
+    #[test]
+    fn discovery_errors_keep_previous_state() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A")]);
+        let mut player = player_with(&backend);
+        player.update_from_backend().unwrap();
+
+        backend.state.borrow_mut().fail_discovery = true;
+        assert!(player.update_from_backend().is_err());
+        assert!(player.get_current_player().is_some());
+        assert!(player.bus_error().is_none());
+    }
+
+    #[test]
+    fn recovers_after_session_bus_returns() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A")]);
+        let mut player = player_with(&backend);
+        player.update_from_backend().unwrap();
+
+        backend.state.borrow_mut().bus_down = true;
+        assert!(player.update_from_backend().is_err());
+        assert_eq!(player.bus_error(), Some("no bus"));
+        assert!(player.get_current_player().is_none());
+
+        backend.state.borrow_mut().bus_down = false;
+        player.update_from_backend().unwrap();
+        assert!(player.bus_error().is_none());
+        assert!(player.get_current_player().is_some());
+    }
+
+    fn episode(position: u64) -> FakePlayer {
+        FakePlayer::new("a", "Podcasts")
+            .status(PlaybackStatus::Playing)
+            .position(Duration::from_secs(position))
+            .with("xesam:title", "Episode 1")
+            .with("xesam:url", "https://example.com/episode-1.mp3")
+            .with("mpris:length", 3_600_000_000i64)
+    }
+
+    #[test]
+    fn offers_to_resume_long_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("positions.json");
+
+        // Listen up to 20 minutes in, then quit
+        let backend = FakeBackend::with_players(vec![episode(1200)]);
+        let mut player = player_with(&backend);
+        player.set_resume(ResumeConfig::default(), PositionStore::load(&path).unwrap());
+        player.update_from_backend().unwrap();
+        assert!(player.resume_offer().is_none());
+        player.save_positions();
+
+        // The item starts over in a new session
+        let backend = FakeBackend::with_players(vec![episode(0)]);
+        let mut player = player_with(&backend);
+        player.set_resume(ResumeConfig::default(), PositionStore::load(&path).unwrap());
+        player.update_from_backend().unwrap();
+        let offer = player.resume_offer().unwrap();
+        assert_eq!(offer.position, 1200);
+        assert_eq!(offer.title.as_deref(), Some("Episode 1"));
+
+        // Playing on before answering keeps the saved position
+        backend.set_players(vec![episode(5)]);
+        player.update_from_backend().unwrap();
+        player.accept_resume();
+        assert!(player.resume_offer().is_none());
+        assert_eq!(
+            backend.sent(),
+            vec![(
+                "a".to_string(),
+                PlayerCommand::SetPosition {
+                    track_id: "/Podcasts/track/0".to_string(),
+                    position: Duration::from_secs(1200),
+                }
+            )]
+        );
+    }
+
+    #[test]
+    fn short_items_are_not_remembered() {
+        let backend = FakeBackend::with_players(vec![episode(1200).with("mpris:length", 240_000_000i64)]);
+        let mut player = player_with(&backend);
+        player.set_resume(ResumeConfig::default(), PositionStore::default());
+        player.update_from_backend().unwrap();
+
+        backend.set_players(vec![FakePlayer::new("a", "Podcasts")]);
+        player.update_from_backend().unwrap();
+        backend.set_players(vec![episode(0).with("mpris:length", 240_000_000i64)]);
+        player.update_from_backend().unwrap();
+        assert!(player.resume_offer().is_none());
+    }
+
+    #[test]
+    fn saved_positions_are_kept_until_offered() {
+        let mut positions = PositionStore::default();
+        let saved = |position| TrackInfo { position, duration: 3600, ..TrackInfo::default() };
+        positions.record("https://example.com/episode-1.mp3", &saved(1200));
+        positions.record("https://example.com/episode-2.mp3", &saved(600));
+        let second = |position| {
+            FakePlayer::new("b", "Radio")
+                .status(PlaybackStatus::Playing)
+                .position(Duration::from_secs(position))
+                .with("xesam:url", "https://example.com/episode-2.mp3")
+                .with("mpris:length", 3_600_000_000i64)
+        };
+
+        // Without a track id there is nothing to offer, but the position stays
+        let backend = FakeBackend::with_players(vec![episode(0).with("mpris:trackid", 0i64)]);
+        let mut player = player_with(&backend);
+        player.set_resume(ResumeConfig::default(), positions);
+        player.update_from_backend().unwrap();
+        backend.set_players(vec![episode(30).with("mpris:trackid", 0i64)]);
+        player.update_from_backend().unwrap();
+        assert!(player.resume_offer().is_none());
+
+        // Once it has one, the offer is made; another player waits its turn
+        backend.set_players(vec![episode(31), second(0)]);
+        player.update_from_backend().unwrap();
+        assert_eq!(player.resume_offer().unwrap().position, 1200);
+        backend.set_players(vec![episode(32), second(5)]);
+        player.update_from_backend().unwrap();
+        assert_eq!(player.resume_offer().unwrap().player_id, "a");
+
+        player.dismiss_resume();
+        player.update_from_backend().unwrap();
+        let offer = player.resume_offer().unwrap();
+        assert_eq!((offer.player_id.as_str(), offer.position), ("b", 600));
+    }
+
+    #[test]
+    fn rate_steps_stay_within_player_limits() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A").rate(1.75, 0.5, 2.0)]);
+        let mut player = player_with(&backend);
+        player.update();
+
+        player.step_rate(true);
+        player.step_rate(true);
+        player.step_rate(false);
+        player.reset_rate();
+        assert_eq!(
+            backend.sent(),
+            vec![
+                ("a".to_string(), PlayerCommand::SetRate(2.0)),
+                ("a".to_string(), PlayerCommand::SetRate(1.75)),
+                ("a".to_string(), PlayerCommand::SetRate(1.0)),
+            ]
+        );
+        assert_eq!(player.get_current_track().rate.map(|rate| rate.current), Some(1.0));
+
+        // The time left stays computed from the speed the player is using
+        backend.state.borrow_mut().fail_commands = true;
+        player.step_rate(true);
+        assert_eq!(player.get_current_track().rate.map(|rate| rate.current), Some(1.0));
+    }
+
+    #[test]
+    fn remaining_time_takes_rate_into_account() {
+        let backend = FakeBackend::with_players(vec![episode(600).rate(1.5, 0.5, 3.0)]);
+        let mut player = player_with(&backend);
+        player.update();
+        assert_eq!(player.get_current_track().remaining(), 2000);
+
+        // Players without a rate never change speed
+        backend.set_players(vec![episode(600)]);
+        player.refresh_soon();
+        player.update();
+        assert_eq!(player.get_current_track().remaining(), 3000);
+
+        // A rate of 0 is a pause, not an endless wait
+        backend.set_players(vec![episode(600).rate(0.0, 0.0, 3.0)]);
+        player.refresh_soon();
+        player.update();
+        assert_eq!(player.get_current_track().remaining(), 3000);
+    }
+
+    #[test]
+    fn unsupported_commands_are_not_sent() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A").capabilities(Capabilities {
+            can_control: false,
+            ..Capabilities::default()
+        })]);
+        let mut player = player_with(&backend);
+        player.update();
+
+        player.toggle_play_pause();
+        assert!(backend.sent().is_empty());
+        let message = player.status_message().map(|m| m.to_string());
+        assert_eq!(message.as_deref(), Some("A does not support play/pause"));
+        assert!(!player.get_current_capabilities().can_control);
+    }
+
+    #[test]
+    fn volume_steps_are_clamped() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "A").volume(0.98)]);
+        let mut player = player_with(&backend);
+        player.update();
+
+        player.step_volume(0.05);
+        player.step_volume(-0.05);
+        assert_eq!(
+            backend.sent(),
+            vec![
+                ("a".to_string(), PlayerCommand::SetVolume(1.0)),
+                ("a".to_string(), PlayerCommand::SetVolume(0.95)),
+            ]
+        );
+        assert_eq!(player.get_current_track().volume, Some(0.95));
+
+        // A volume the player did not take is not shown
+        backend.state.borrow_mut().fail_commands = true;
+        player.step_volume(0.05);
+        assert_eq!(player.get_current_track().volume, Some(0.95));
+    }
+
+    #[test]
+    fn players_that_can_rate_get_a_rating() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "MPD")
+            .with("xesam:title", "Song")
+            .capabilities(Capabilities {
+                can_rate: true,
+                ..Capabilities::default()
+            })]);
+        let mut player = player_with(&backend);
+        player.update();
+
+        player.toggle_like();
+        assert!(player.is_liked());
+        player.toggle_like();
+        assert!(!player.is_liked());
+        assert_eq!(
+            backend.sent(),
+            vec![
+                ("a".to_string(), PlayerCommand::SetRating(1.0)),
+                ("a".to_string(), PlayerCommand::SetRating(0.0)),
+            ]
+        );
+        assert_eq!(player.favorites().list().count(), 0);
+    }
+
+    #[test]
+    fn likes_keep_other_ratings_and_failed_ratings() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "MPD")
+            .with("xesam:title", "Song")
+            .with("xesam:userRating", 0.6)
+            .capabilities(Capabilities {
+                can_rate: true,
+                ..Capabilities::default()
+            })]);
+        let mut player = player_with(&backend);
+        player.update();
+
+        // Three stars are not a like, so F raises them instead of clearing them
+        assert!(!player.is_liked());
+        backend.state.borrow_mut().fail_commands = true;
+        player.toggle_like();
+        assert!(!player.is_liked());
+        assert_eq!(player.get_current_track().user_rating, Some(0.6));
+
+        backend.state.borrow_mut().fail_commands = false;
+        player.toggle_like();
+        assert!(player.is_liked());
+        assert_eq!(backend.sent(), vec![("a".to_string(), PlayerCommand::SetRating(1.0))]);
+    }
+
+    #[test]
+    fn favorites_do_not_clear_ratings() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "MPD")
+            .with("xesam:title", "Song")
+            .capabilities(Capabilities {
+                can_rate: true,
+                ..Capabilities::default()
+            })]);
+        let mut player = player_with(&backend);
+        player.update();
+        // Liked earlier in a player that could not rate it
+        let track = player.get_current_track().clone();
+        player.favorites.toggle(&track, "Browser");
+        assert!(player.is_liked());
+
+        player.toggle_like();
+        assert_eq!(backend.sent(), vec![("a".to_string(), PlayerCommand::SetRating(1.0))]);
+    }
+
+    #[test]
+    fn other_players_like_into_favorites() {
+        let backend = FakeBackend::with_players(vec![FakePlayer::new("a", "Browser").with("xesam:title", "Song")]);
+        let mut player = player_with(&backend);
+        player.update();
+
+        player.toggle_like();
+        assert!(player.is_liked());
+        assert!(backend.sent().is_empty());
+        assert_eq!(player.favorites().list().next().unwrap().player, "Browser");
+        let message = player.status_message().map(|m| m.to_string());
+        assert_eq!(message.as_deref(), Some("Added \"Song\" to favorites"));
+    }
+}
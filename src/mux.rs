@@ -0,0 +1,341 @@
+use anyhow::{bail, Context, Result};
+use crossterm::terminal;
+use log::{debug, info};
+use ratatui::buffer::Buffer;
+use ratatui_image::{
+    picker::{
+        cap_parser::{Parser, QueryStdioOptions, Response},
+        Capability, Picker, ProtocolType,
+    },
+    FontSize,
+};
+use std::{
+    env,
+    io::{self, Read, Write},
+    process::Command,
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+/// GNU screen drops DCS strings longer than this
+const SCREEN_CHUNK: usize = 760;
+
+/// How long the terminal has to answer a query, as long as ratatui-image gives it
+const QUERY_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// A terminal multiplexer between us and the terminal, which only lets
+/// graphics through when they are wrapped in its passthrough sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    /// The multiplexer we run in, if any
+    pub fn detect() -> Option<Self> {
+        detect_from(env::var("TMUX").ok(), env::var("STY").ok(), env::var("TERM").ok())
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Multiplexer::Tmux => "tmux",
+            Multiplexer::Screen => "screen",
+        }
+    }
+
+    /// Whether the multiplexer passes wrapped sequences on. tmux 3.3 and
+    /// later drop them unless `allow-passthrough` is on; screen always does.
+    pub fn allows_passthrough(self) -> bool {
+        match self {
+            Multiplexer::Tmux => {
+                let output = Command::new("tmux").args(["show-options", "-pAv", "allow-passthrough"]).output();
+                match output {
+                    Ok(output) if output.status.success() => {
+                        let value = String::from_utf8_lossy(&output.stdout);
+                        debug!("tmux allow-passthrough is {:?}", value.trim());
+                        passthrough_enabled(&value)
+                    }
+                    // Older versions have no such option and pass everything
+                    _ => true,
+                }
+            }
+            Multiplexer::Screen => true,
+        }
+    }
+
+    /// Wrap one escape sequence so the multiplexer hands it to the terminal
+    fn wrap(self, sequence: &str) -> String {
+        match self {
+            Multiplexer::Tmux => format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b")),
+            Multiplexer::Screen => {
+                // The sequence's own terminator would end screen's string early,
+                // so it is split between two strings: ESC ends one, "\" starts the next
+                let mut wrapped = String::new();
+                for part in sequence.split_inclusive("\x1b\\") {
+                    let (head, backslash) = match part.strip_suffix('\\') {
+                        Some(head) if head.ends_with('\x1b') => (head, true),
+                        _ => (part, false),
+                    };
+                    for chunk in chunks(head, SCREEN_CHUNK) {
+                        wrapped.push_str(&format!("\x1bP{}\x1b\\", chunk));
+                    }
+                    if backslash {
+                        wrapped.push_str("\x1bP\\\x1b\\");
+                    }
+                }
+                wrapped
+            }
+        }
+    }
+}
+
+/// Ask the terminal behind tmux what it supports, with the query wrapped for
+/// passthrough. ratatui-image only wraps its own query when `TERM` or
+/// `TERM_PROGRAM` say tmux, so with tmux's common `TERM=screen-256color` only
+/// tmux itself would answer. Returns the picker and the reported capabilities.
+pub fn query_through_tmux() -> Result<(Picker, Vec<Capability>)> {
+    let was_raw = terminal::is_raw_mode_enabled()?;
+    if !was_raw {
+        terminal::enable_raw_mode()?;
+    }
+    let responses = read_responses(&Parser::query(true, QueryStdioOptions { text_sizing_protocol: false }));
+    if !was_raw {
+        terminal::disable_raw_mode()?;
+    }
+    let fallback = terminal::window_size()
+        .ok()
+        .filter(|size| size.width > 0 && size.height > 0 && size.columns > 0 && size.rows > 0)
+        .map(|size| (size.width / size.columns, size.height / size.rows));
+    picker_from(responses?, fallback)
+}
+
+/// Send `query` and collect the answers up to the closing status report
+fn read_responses(query: &str) -> Result<Vec<Response>> {
+    let mut stdout = io::stdout();
+    stdout.write_all(query.as_bytes())?;
+    stdout.flush()?;
+
+    let (tx, rx) = mpsc::channel();
+    // Like ratatui-image's own query, a terminal that never answers leaves this thread waiting
+    thread::spawn(move || {
+        let mut parser = Parser::new();
+        let mut responses = Vec::new();
+        let mut buffer = [0; 64];
+        let result = loop {
+            match io::stdin().read(&mut buffer) {
+                Ok(0) => break Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(read) => {
+                    let pushed: Vec<Response> =
+                        buffer[..read].iter().flat_map(|&c| parser.push(char::from(c))).collect();
+                    if let Some(end) = pushed.iter().position(|response| *response == Response::Status) {
+                        responses.extend(pushed.into_iter().take(end));
+                        break Ok(responses);
+                    }
+                    responses.extend(pushed);
+                }
+                Err(e) => break Err(e),
+            }
+        };
+        let _ = tx.send(result);
+    });
+    let result = rx.recv_timeout(QUERY_TIMEOUT).context("The terminal did not answer")?;
+    Ok(result?)
+}
+
+/// A picker for what the terminal answered, the way ratatui-image makes one
+fn picker_from(responses: Vec<Response>, fallback: Option<FontSize>) -> Result<(Picker, Vec<Capability>)> {
+    let mut detected = None;
+    let mut font_size = None;
+    let mut capabilities = Vec::new();
+    for response in responses {
+        match response {
+            Response::Kitty => {
+                detected = Some(ProtocolType::Kitty);
+                capabilities.push(Capability::Kitty);
+            }
+            Response::Sixel => {
+                detected = detected.or(Some(ProtocolType::Sixel));
+                capabilities.push(Capability::Sixel);
+            }
+            Response::RectangularOps => capabilities.push(Capability::RectangularOps),
+            Response::CellSize(size) => {
+                font_size = font_size.or(size);
+                capabilities.push(Capability::CellSize(size));
+            }
+            Response::CursorPositionReport(..) | Response::Status => {}
+        }
+    }
+    let Some(font_size) = font_size.or(fallback) else {
+        bail!("The terminal did not report its font size");
+    };
+    let mut picker = Picker::from_fontsize(font_size);
+    // from_fontsize has guessed iTerm2 from the environment if it could, like a query would
+    if let (ProtocolType::Halfblocks, Some(detected)) = (picker.protocol_type(), detected) {
+        picker.set_protocol_type(detected);
+    }
+    Ok((picker, capabilities))
+}
+
+fn detect_from(tmux: Option<String>, sty: Option<String>, term: Option<String>) -> Option<Multiplexer> {
+    let set = |value: &Option<String>| value.as_ref().is_some_and(|v| !v.is_empty());
+    if set(&tmux) {
+        Some(Multiplexer::Tmux)
+    } else if set(&sty) || term.is_some_and(|term| term.starts_with("screen")) {
+        Some(Multiplexer::Screen)
+    } else {
+        None
+    }
+}
+
+fn passthrough_enabled(value: &str) -> bool {
+    value.trim() != "off"
+}
+
+/// Split `text` into pieces of at most `size` bytes, on character boundaries
+fn chunks(text: &str, size: usize) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while rest.len() > size {
+        let mut end = size;
+        while !rest.is_char_boundary(end) {
+            end -= 1;
+        }
+        let (piece, tail) = rest.split_at(end);
+        pieces.push(piece);
+        rest = tail;
+    }
+    if !rest.is_empty() {
+        pieces.push(rest);
+    }
+    pieces
+}
+
+/// Wrap the graphics sequences (kitty APC, sixel DCS, iTerm2 OSC) in `symbol`;
+/// `None` if there are none or they are wrapped already
+fn wrap_graphics(symbol: &str, multiplexer: Multiplexer) -> Option<String> {
+    if !symbol.contains('\x1b') || symbol.contains("\x1bPtmux;") {
+        return None;
+    }
+    let mut wrapped = String::with_capacity(symbol.len() * 2);
+    let mut changed = false;
+    let mut rest = symbol;
+    while let Some(start) = rest.find('\x1b') {
+        wrapped.push_str(&rest[..start]);
+        let sequence = &rest[start..];
+        let end = match sequence.as_bytes().get(1) {
+            Some(b'_') | Some(b'P') => sequence.find("\x1b\\").map(|end| end + 2),
+            Some(b']') => {
+                let bel = sequence.find('\x07').map(|end| end + 1);
+                let st = sequence.find("\x1b\\").map(|end| end + 2);
+                bel.into_iter().chain(st).min()
+            }
+            // Cursor movement and colors work as they are
+            _ => None,
+        };
+        match end {
+            Some(end) => {
+                wrapped.push_str(&multiplexer.wrap(&sequence[..end]));
+                changed = true;
+                rest = &sequence[end..];
+            }
+            None => {
+                wrapped.push('\x1b');
+                rest = &sequence[1..];
+            }
+        }
+    }
+    wrapped.push_str(rest);
+    changed.then_some(wrapped)
+}
+
+/// Wrap every graphics sequence drawn into `buf` for `multiplexer`
+pub fn passthrough(buf: &mut Buffer, multiplexer: Multiplexer) {
+    for cell in buf.content.iter_mut() {
+        if let Some(wrapped) = wrap_graphics(cell.symbol(), multiplexer) {
+            cell.set_symbol(&wrapped);
+        }
+    }
+}
+
+/// Log whether covers can be drawn as graphics inside the multiplexer
+pub fn log_passthrough(multiplexer: Multiplexer, allowed: bool) {
+    if allowed {
+        info!("Running in {}, wrapping image sequences for passthrough", multiplexer.name());
+    } else {
+        info!(
+            "Running in {} without passthrough (set allow-passthrough on), drawing covers as text",
+            multiplexer.name()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_the_multiplexer_from_the_environment() {
+        let some = |s: &str| Some(s.to_string());
+        let tmux = some("/tmp/tmux-1000/default,1,0");
+        assert_eq!(detect_from(tmux, None, some("screen-256color")), Some(Multiplexer::Tmux));
+        assert_eq!(detect_from(None, some("1234.pts-0.host"), some("xterm")), Some(Multiplexer::Screen));
+        assert_eq!(detect_from(None, None, some("screen.xterm-256color")), Some(Multiplexer::Screen));
+        assert_eq!(detect_from(some(""), None, some("xterm-kitty")), None);
+    }
+
+    #[test]
+    fn tmux_wraps_graphics_and_doubles_escapes() {
+        let symbol = "\x1b_Gi=1;AAAA\x1b\\\x1b[s\u{10EEEE}";
+        assert_eq!(
+            wrap_graphics(symbol, Multiplexer::Tmux).unwrap(),
+            "\x1bPtmux;\x1b\x1b_Gi=1;AAAA\x1b\x1b\\\x1b\\\x1b[s\u{10EEEE}"
+        );
+        // iTerm2 images end with BEL
+        assert_eq!(
+            wrap_graphics("\x1b]1337;File=:AA\x07", Multiplexer::Tmux).unwrap(),
+            "\x1bPtmux;\x1b\x1b]1337;File=:AA\x07\x1b\\"
+        );
+    }
+
+    #[test]
+    fn plain_text_and_wrapped_sequences_are_left_alone() {
+        assert_eq!(wrap_graphics("abc", Multiplexer::Tmux), None);
+        assert_eq!(wrap_graphics("\x1b[31m", Multiplexer::Tmux), None);
+        assert_eq!(wrap_graphics("\x1bPtmux;\x1b\x1bPq#0\x1b\x1b\\\x1b\\", Multiplexer::Tmux), None);
+    }
+
+    #[test]
+    fn screen_splits_long_sequences_and_terminators() {
+        let sixel = format!("\x1bPq{}\x1b\\", "#".repeat(1000));
+        let wrapped = wrap_graphics(&sixel, Multiplexer::Screen).unwrap();
+
+        let strings: Vec<&str> = wrapped.split_inclusive("\x1b\\").collect();
+        assert!(strings.iter().all(|s| s.starts_with("\x1bP") && s.len() <= SCREEN_CHUNK + 4));
+        // What the terminal gets once screen unwraps every string
+        let unwrapped: String = strings.iter().map(|s| &s[2..s.len() - 2]).collect();
+        assert_eq!(unwrapped, sixel);
+    }
+
+    #[test]
+    fn answers_from_behind_tmux_make_a_picker() {
+        let responses = vec![Response::Sixel, Response::Kitty, Response::CellSize(Some((9, 18)))];
+        let (picker, capabilities) = picker_from(responses, Some((10, 20))).unwrap();
+        assert_eq!(picker.font_size(), (9, 18));
+        assert!(capabilities.contains(&Capability::Kitty) && capabilities.contains(&Capability::Sixel));
+
+        // Without a cell size the window size has to do, and without either there is no picker
+        let (picker, capabilities) = picker_from(vec![Response::Sixel], Some((10, 20))).unwrap();
+        assert_eq!(picker.font_size(), (10, 20));
+        assert_eq!(capabilities, vec![Capability::Sixel]);
+        assert!(picker_from(vec![Response::Sixel], None).is_err());
+    }
+
+    #[test]
+    fn tmux_passthrough_setting() {
+        assert!(passthrough_enabled("on\n"));
+        assert!(passthrough_enabled("all\n"));
+        assert!(!passthrough_enabled("off\n"));
+    }
+}
@@ -0,0 +1,211 @@
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+use crate::backend::PlayerCommand;
+use crate::music::PlayerState;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// Pause every other player when one starts playing
+    pub exclusive: bool,
+    /// Players that are never paused, matched case-insensitively against
+    /// part of the player name or bus name, e.g. "zoom"
+    pub never_pause: Vec<String>,
+    /// Resume the players paused for a player once it stops playing
+    pub resume_previous: bool,
+}
+
+/// Exclusive playback: when a player starts, pause the others.
+///
+/// Fed with the player states read on every update, it returns the commands
+/// to send.
+#[derive(Debug, Default)]
+pub struct PlaybackPolicy {
+    config: PolicyConfig,
+    /// Players that were playing on the last update, `None` before the first
+    was_playing: Option<HashSet<String>>,
+    /// (paused player, player it was paused for), oldest first
+    paused_for: Vec<(String, String)>,
+}
+
+impl PlaybackPolicy {
+    pub fn new(config: PolicyConfig) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.config.exclusive
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.config.exclusive = enabled;
+        self.paused_for.clear();
+    }
+
+    fn is_exempt(&self, player: &PlayerState) -> bool {
+        let identity = player.entry.identity.to_lowercase();
+        let id = player.entry.id.to_lowercase();
+        self.config.never_pause.iter().any(|name| {
+            let name = name.to_lowercase();
+            identity.contains(&name) || id.contains(&name)
+        })
+    }
+
+    pub fn apply(&mut self, players: &[PlayerState]) -> Vec<(String, PlayerCommand)> {
+        let playing: HashSet<String> = players
+            .iter()
+            .filter(|p| p.track.is_playing)
+            .map(|p| p.entry.id.clone())
+            .collect();
+        // The first update only records what is already playing
+        let Some(was_playing) = self.was_playing.replace(playing.clone()) else {
+            return Vec::new();
+        };
+        if !self.config.exclusive {
+            return Vec::new();
+        }
+
+        let mut commands = Vec::new();
+
+        // Players resumed by hand are no longer waiting to be resumed
+        self.paused_for.retain(|(paused, _)| !playing.contains(paused));
+
+        if let Some(started) = players
+            .iter()
+            .rev()
+            .find(|p| playing.contains(&p.entry.id) && !was_playing.contains(&p.entry.id))
+        {
+            for other in players {
+                if other.entry.id != started.entry.id
+                    && playing.contains(&other.entry.id)
+                    && !self.is_exempt(other)
+                {
+                    info!("{} started playing, pausing {}", started.entry.identity, other.entry.identity);
+                    commands.push((other.entry.id.clone(), PlayerCommand::Pause));
+                    self.paused_for.push((other.entry.id.clone(), started.entry.id.clone()));
+                }
+            }
+            return commands;
+        }
+
+        if self.config.resume_previous {
+            // Resume the most recently paused player once the one it made way for stops,
+            // unless something else is playing by now
+            let others_playing = players
+                .iter()
+                .any(|p| playing.contains(&p.entry.id) && !self.is_exempt(p));
+            let finished = self
+                .paused_for
+                .iter()
+                .rposition(|(_, paused_for)| !playing.contains(paused_for));
+            if let (false, Some(index)) = (others_playing, finished) {
+                let (paused, _) = self.paused_for.remove(index);
+                if players.iter().any(|p| p.entry.id == paused) {
+                    info!("Resuming {}", paused);
+                    commands.push((paused, PlayerCommand::Play));
+                }
+            }
+        }
+
+        commands
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::{Capabilities, PlayerEntry};
+    use crate::music::TrackInfo;
+
+    fn player(id: &str, is_playing: bool) -> PlayerState {
+        PlayerState {
+            entry: PlayerEntry {
+                id: format!("org.mpris.MediaPlayer2.{}", id),
+                identity: id.to_string(),
+            },
+            track: TrackInfo {
+                is_playing,
+                ..TrackInfo::default()
+            },
+            capabilities: Capabilities::default(),
+        }
+    }
+
+    fn id(name: &str) -> String {
+        format!("org.mpris.MediaPlayer2.{}", name)
+    }
+
+    fn exclusive(never_pause: &[&str], resume_previous: bool) -> PlaybackPolicy {
+        PlaybackPolicy::new(PolicyConfig {
+            exclusive: true,
+            never_pause: never_pause.iter().map(|s| s.to_string()).collect(),
+            resume_previous,
+        })
+    }
+
+    #[test]
+    fn disabled_policy_does_nothing() {
+        let mut policy = PlaybackPolicy::default();
+        policy.apply(&[player("a", true), player("b", false)]);
+        assert!(policy.apply(&[player("a", true), player("b", true)]).is_empty());
+    }
+
+    #[test]
+    fn first_update_does_not_pause() {
+        let mut policy = exclusive(&[], false);
+        assert!(policy.apply(&[player("a", true), player("b", true)]).is_empty());
+    }
+
+    #[test]
+    fn starting_player_pauses_others() {
+        let mut policy = exclusive(&[], false);
+        policy.apply(&[player("a", true), player("b", false), player("c", false)]);
+
+        let commands = policy.apply(&[player("a", true), player("b", true), player("c", false)]);
+        assert_eq!(commands, vec![(id("a"), PlayerCommand::Pause)]);
+    }
+
+    #[test]
+    fn exempt_players_keep_playing() {
+        let mut policy = exclusive(&["CALL"], false);
+        policy.apply(&[player("call", true), player("music", false)]);
+
+        assert!(policy.apply(&[player("call", true), player("music", true)]).is_empty());
+    }
+
+    #[test]
+    fn resumes_previous_player_when_new_one_stops() {
+        let mut policy = exclusive(&[], true);
+        policy.apply(&[player("music", true), player("video", false)]);
+        policy.apply(&[player("music", true), player("video", true)]);
+
+        // Paused by the policy, video still playing
+        assert!(policy.apply(&[player("music", false), player("video", true)]).is_empty());
+        assert_eq!(
+            policy.apply(&[player("music", false), player("video", false)]),
+            vec![(id("music"), PlayerCommand::Play)]
+        );
+        // Only once
+        assert!(policy.apply(&[player("music", false), player("video", false)]).is_empty());
+    }
+
+    #[test]
+    fn does_not_resume_when_resumed_by_hand() {
+        let mut policy = exclusive(&[], true);
+        policy.apply(&[player("music", true), player("video", false)]);
+        policy.apply(&[player("music", true), player("video", true)]);
+        policy.apply(&[player("music", false), player("video", true)]);
+
+        // The user starts music again, which pauses video
+        assert_eq!(
+            policy.apply(&[player("music", true), player("video", true)]),
+            vec![(id("video"), PlayerCommand::Pause)]
+        );
+        assert!(policy.apply(&[player("music", true), player("video", false)]).is_empty());
+    }
+}
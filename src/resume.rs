@@ -0,0 +1,204 @@
+use anyhow::Result;
+use log::{error, info};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::json_store;
+use crate::music::TrackInfo;
+
+/// How many items the store remembers before dropping the oldest
+const MAX_ENTRIES: usize = 500;
+
+/// How often changed positions are written to disk
+const FLUSH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Items closer than this to their end count as finished and are forgotten
+const FINISHED_MARGIN: u64 = 30;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResumeConfig {
+    /// Remember positions of long items
+    pub enabled: bool,
+    /// Only items at least this long are remembered (podcasts, audiobooks)
+    pub min_duration_minutes: u64,
+    /// Seek to the saved position without asking
+    pub auto_resume: bool,
+}
+
+impl Default for ResumeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_duration_minutes: 20,
+            auto_resume: false,
+        }
+    }
+}
+
+impl ResumeConfig {
+    /// Whether positions of `track` should be remembered
+    pub fn applies_to(&self, track: &TrackInfo) -> bool {
+        self.enabled && track.duration >= self.min_duration_minutes * 60
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedPosition {
+    /// Seconds into the item
+    pub position: u64,
+    pub title: Option<String>,
+    /// Unix time of the last update, used to drop old entries
+    pub updated: u64,
+}
+
+/// The key an item is remembered under: its URL, which stays the same
+/// across players, or else the player's track id.
+pub fn item_key(track: &TrackInfo) -> Option<String> {
+    track
+        .url
+        .clone()
+        .or_else(|| track.track_id.as_ref().map(|id| format!("trackid:{}", id)))
+}
+
+/// Last positions of long items, stored as JSON.
+#[derive(Debug, Default)]
+pub struct PositionStore {
+    path: Option<PathBuf>,
+    entries: HashMap<String, SavedPosition>,
+    dirty: bool,
+    last_flush: Option<Instant>,
+}
+
+impl PositionStore {
+    /// Load the store from `path`; a missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            entries: json_store::load(path, "position store")?,
+            ..Self::default()
+        })
+    }
+
+    pub fn get(&self, key: &str) -> Option<&SavedPosition> {
+        self.entries.get(key)
+    }
+
+    /// Remember where `track` is, or forget it once it is (nearly) finished
+    pub fn record(&mut self, key: &str, track: &TrackInfo) {
+        if track.position + FINISHED_MARGIN >= track.duration {
+            if self.entries.remove(key).is_some() {
+                self.dirty = true;
+            }
+            return;
+        }
+        if self.entries.get(key).is_some_and(|saved| saved.position == track.position) {
+            return;
+        }
+
+        let updated = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.entries.insert(
+            key.to_string(),
+            SavedPosition {
+                position: track.position,
+                title: track.title.clone(),
+                updated,
+            },
+        );
+        if self.entries.len() > MAX_ENTRIES {
+            if let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, saved)| saved.updated)
+                .map(|(key, _)| key.clone())
+            {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.dirty = true;
+    }
+
+    /// Write changes to disk if the last write was long enough ago
+    pub fn flush_if_due(&mut self) {
+        if self.last_flush.is_none_or(|at| at.elapsed() >= FLUSH_INTERVAL) {
+            self.flush();
+        }
+    }
+
+    /// Write changes to disk now
+    pub fn flush(&mut self) {
+        if !self.dirty {
+            return;
+        }
+        let Some(ref path) = self.path else {
+            return;
+        };
+        match json_store::save_atomically(path, &self.entries) {
+            Ok(()) => {
+                self.dirty = false;
+                info!("Saved {} playback positions", self.entries.len());
+            }
+            Err(e) => error!("Failed to save playback positions: {:#}", e),
+        }
+        self.last_flush = Some(Instant::now());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(position: u64, duration: u64) -> TrackInfo {
+        TrackInfo {
+            title: Some("Episode".to_string()),
+            url: Some("https://example.com/episode.mp3".to_string()),
+            position,
+            duration,
+            ..TrackInfo::default()
+        }
+    }
+
+    #[test]
+    fn round_trips_through_disk() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("positions.json");
+
+        let mut store = PositionStore::load(&path).unwrap();
+        store.record("episode", &track(600, 3600));
+        store.flush();
+
+        let store = PositionStore::load(&path).unwrap();
+        let saved = store.get("episode").unwrap();
+        assert_eq!(saved.position, 600);
+        assert_eq!(saved.title.as_deref(), Some("Episode"));
+    }
+
+    #[test]
+    fn finished_items_are_forgotten() {
+        let mut store = PositionStore::default();
+        store.record("episode", &track(600, 3600));
+        store.record("episode", &track(3590, 3600));
+        assert!(store.get("episode").is_none());
+    }
+
+    #[test]
+    fn url_is_preferred_over_track_id() {
+        let mut track = track(0, 3600);
+        track.track_id = Some("/org/mpd/Tracks/1".to_string());
+        assert_eq!(item_key(&track).as_deref(), Some("https://example.com/episode.mp3"));
+
+        track.url = None;
+        assert_eq!(item_key(&track).as_deref(), Some("trackid:/org/mpd/Tracks/1"));
+    }
+
+    #[test]
+    fn only_long_items_apply() {
+        let config = ResumeConfig::default();
+        assert!(config.applies_to(&track(0, 3600)));
+        assert!(!config.applies_to(&track(0, 240)));
+    }
+}
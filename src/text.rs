@@ -0,0 +1,143 @@
+use ratatui::text::{Line, Span};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const ELLIPSIS: &str = "…";
+
+/// Columns `text` takes on screen, counted per grapheme like ratatui does,
+/// so CJK characters take two and combining marks none
+pub fn width(text: &str) -> usize {
+    text.graphemes(true).map(UnicodeWidthStr::width).sum()
+}
+
+/// `text` cut to at most `max` columns with an ellipsis at the end; never
+/// splits a grapheme, so wide characters and emoji stay whole
+pub fn truncate(text: &str, max: usize) -> String {
+    if width(text) <= max {
+        return text.to_string();
+    }
+    cut(text, max)
+}
+
+/// The start of `text` and an ellipsis, in at most `max` columns
+fn cut(text: &str, max: usize) -> String {
+    if max == 0 {
+        return String::new();
+    }
+    let mut shown = String::new();
+    let mut used = 0;
+    for grapheme in text.graphemes(true) {
+        let grapheme_width = grapheme.width();
+        if used + grapheme_width > max - 1 {
+            break;
+        }
+        shown.push_str(grapheme);
+        used += grapheme_width;
+    }
+    shown.push_str(ELLIPSIS);
+    shown
+}
+
+/// Every line of `text` truncated to `max` columns
+pub fn truncate_lines(text: &str, max: usize) -> String {
+    text.lines().map(|line| truncate(line, max)).collect::<Vec<_>>().join("\n")
+}
+
+/// `line` cut to at most `max` columns, the ellipsis styled like the span it cuts
+pub fn truncate_line(line: Line<'_>, max: usize) -> Line<'_> {
+    if line.width() <= max {
+        return line;
+    }
+    let mut spans = Vec::new();
+    let mut used = 0;
+    for span in line.spans {
+        let span_width = width(&span.content);
+        if used + span_width < max {
+            used += span_width;
+            spans.push(span);
+        } else {
+            spans.push(Span::styled(cut(&span.content, max - used), span.style));
+            break;
+        }
+    }
+    Line {
+        spans,
+        ..Line::default().style(line.style)
+    }
+    .alignment(line.alignment.unwrap_or_default())
+}
+
+/// The end of `text` that fits in `max` columns, for input that grows at the end
+pub fn tail(text: &str, max: usize) -> &str {
+    let mut used = 0;
+    let mut start = text.len();
+    for (index, grapheme) in text.grapheme_indices(true).rev() {
+        used += grapheme.width();
+        if used > max {
+            break;
+        }
+        start = index;
+    }
+    &text[start..]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::style::{Color, Style};
+
+    #[test]
+    fn measures_wide_and_zero_width_graphemes() {
+        assert_eq!(width("abc"), 3);
+        assert_eq!(width("周杰伦"), 6);
+        assert_eq!(width("🎵"), 2);
+        // e + combining acute accent
+        assert_eq!(width("cafe\u{301}"), 4);
+    }
+
+    #[test]
+    fn truncates_with_an_ellipsis() {
+        assert_eq!(truncate("short", 10), "short");
+        assert_eq!(truncate("exactly", 7), "exactly");
+        assert_eq!(truncate("too long", 5), "too …");
+        assert_eq!(truncate("anything", 0), "");
+        assert_eq!(truncate("anything", 1), "…");
+    }
+
+    #[test]
+    fn never_cuts_wide_characters_or_emoji_in_half() {
+        // The third character would end at column 6, past the 5 left before the ellipsis
+        assert_eq!(truncate("七里香晴天", 6), "七里…");
+        assert_eq!(width(&truncate("七里香晴天", 6)), 5);
+        assert_eq!(truncate("🎵🎶🎸 songs", 6), "🎵🎶…");
+        // A family emoji is one grapheme made of several characters
+        let family = "👨\u{200d}👩\u{200d}👧";
+        assert_eq!(truncate(&format!("{}{} and more", family, family), 6), format!("{}{} …", family, family));
+        // Combining marks stay with their letter
+        assert_eq!(truncate("cafe\u{301} au lait", 5), "cafe\u{301}…");
+        for max in 0..12 {
+            assert!(width(&truncate("晴天 Sunny Day 🌞", max)) <= max);
+        }
+    }
+
+    #[test]
+    fn truncates_styled_lines() {
+        let red = Style::default().fg(Color::Red);
+        let line = Line::from(vec![Span::styled("♥ ", red), Span::raw("晴天"), Span::raw(" - 周杰伦")]);
+        let cut = truncate_line(line.clone(), 7);
+        assert_eq!(cut.width(), 7);
+        assert_eq!(cut.spans[0].style, red);
+        assert_eq!(cut.to_string(), "♥ 晴天…");
+        // A span that would just fit still leaves room for the ellipsis
+        assert_eq!(truncate_line(line.clone(), 6).to_string(), "♥ 晴…");
+        assert_eq!(truncate_line(line.clone(), 20), line);
+    }
+
+    #[test]
+    fn tails_keep_the_end_that_fits() {
+        assert_eq!(tail("/music/abc", 3), "abc");
+        assert_eq!(tail("/音乐/周杰伦", 5), "杰伦");
+        assert_eq!(tail("short", 10), "short");
+        assert_eq!(tail("anything", 0), "");
+    }
+}
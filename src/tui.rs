@@ -0,0 +1,113 @@
+use crossterm::{
+    cursor::Show,
+    event::{DisableFocusChange, DisableMouseCapture, EnableFocusChange, EnableMouseCapture},
+    execute,
+    terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use ratatui::{backend::CrosstermBackend, Terminal};
+use std::{
+    backtrace::Backtrace,
+    io, panic,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use tokio::{
+    signal::unix::{signal, SignalKind},
+    sync::mpsc::{self, UnboundedReceiver},
+};
+
+use log::{error, info};
+
+/// Set while we stop ourselves in `suspend`, which takes the terminal back
+/// itself, so the SIGCONT that continues us is not passed on as well
+static SELF_STOPPED: AtomicBool = AtomicBool::new(false);
+
+/// Process signals the main loop has to react to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AppSignal {
+    /// SIGTERM, SIGHUP or SIGINT: quit after restoring the terminal
+    Terminate,
+    /// SIGTSTP: restore the terminal and stop the process
+    Suspend,
+    /// SIGCONT after being stopped by someone else: take over the terminal again
+    Resume,
+}
+
+/// Put the terminal into raw mode on the alternate screen with mouse capture.
+pub fn init() -> io::Result<Terminal<CrosstermBackend<io::Stdout>>> {
+    enter()?;
+    Terminal::new(CrosstermBackend::new(io::stdout()))
+}
+
+fn enter() -> io::Result<()> {
+    enable_raw_mode()?;
+    execute!(io::stdout(), EnterAlternateScreen, EnableMouseCapture, EnableFocusChange)
+}
+
+/// Give the terminal back to the shell. Safe to call more than once.
+pub fn restore() -> io::Result<()> {
+    disable_raw_mode()?;
+    execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture, DisableFocusChange, Show)
+}
+
+/// Restore the terminal and stop the process like the default SIGTSTP
+/// action would. Returns once the process has been continued.
+pub fn suspend() -> io::Result<()> {
+    info!("Suspending");
+    restore()?;
+    // SIGTSTP itself is handled by us, SIGSTOP cannot be
+    SELF_STOPPED.store(true, Ordering::SeqCst);
+    unsafe {
+        libc::raise(libc::SIGSTOP);
+    }
+    resume()
+}
+
+/// Take over the terminal again after the process was continued.
+pub fn resume() -> io::Result<()> {
+    info!("Resuming");
+    enter()
+}
+
+/// Restore the terminal before the default panic message is printed, and
+/// write the panic with a backtrace to the log file.
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |panic_info| {
+        let _ = restore();
+        error!("{}\n{}", panic_info, Backtrace::force_capture());
+        default_hook(panic_info);
+    }));
+}
+
+/// Listen for process signals on a background task.
+pub fn spawn_signal_listener() -> io::Result<UnboundedReceiver<AppSignal>> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    let mut sighup = signal(SignalKind::hangup())?;
+    let mut sigint = signal(SignalKind::interrupt())?;
+    let mut sigtstp = signal(SignalKind::from_raw(libc::SIGTSTP))?;
+    let mut sigcont = signal(SignalKind::from_raw(libc::SIGCONT))?;
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        loop {
+            let signal = tokio::select! {
+                _ = sigterm.recv() => AppSignal::Terminate,
+                _ = sighup.recv() => AppSignal::Terminate,
+                _ = sigint.recv() => AppSignal::Terminate,
+                _ = sigtstp.recv() => AppSignal::Suspend,
+                _ = sigcont.recv() => {
+                    if SELF_STOPPED.swap(false, Ordering::SeqCst) {
+                        continue;
+                    }
+                    AppSignal::Resume
+                }
+            };
+            info!("Received signal: {:?}", signal);
+            if tx.send(signal).is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(rx)
+}
@@ -0,0 +1,160 @@
+use anyhow::{bail, Context, Result};
+use crate::i18n::Message;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+/// The scheme of `uri`, e.g. "https", or `None` for plain paths
+fn scheme(uri: &str) -> Option<&str> {
+    let (scheme, _) = uri.split_once(':')?;
+    let mut chars = scheme.chars();
+    let valid = chars.next().is_some_and(|c| c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        // Single letters are more likely Windows drives than schemes
+        && scheme.len() > 1;
+    valid.then_some(scheme)
+}
+
+/// Expand a leading `~` to the home directory
+pub fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => {
+            let home = env::var_os("HOME").map(PathBuf::from).unwrap_or_default();
+            home.join(rest.trim_start_matches('/'))
+        }
+        _ => PathBuf::from(path),
+    }
+}
+
+/// Turn what the user typed into a URI: URIs are kept as they are, local
+/// paths must exist and become `file://` URIs.
+pub fn to_uri(input: &str) -> Result<String> {
+    let input = input.trim();
+    if input.is_empty() {
+        bail!(Message::new("open.empty"));
+    }
+    if scheme(input).is_some() {
+        return Ok(input.to_string());
+    }
+
+    let path = expand_home(input);
+    let path = if path.is_absolute() { path } else { env::current_dir()?.join(path) };
+    let path = path
+        .canonicalize()
+        .with_context(|| Message::new("open.missing").arg("path", path.display().to_string()))?;
+    Ok(file_uri(&path))
+}
+
+pub fn file_uri(path: &Path) -> String {
+    let encoded: Vec<String> = path
+        .to_string_lossy()
+        .split('/')
+        .map(|segment| urlencoding::encode(segment).into_owned())
+        .collect();
+    format!("file://{}", encoded.join("/"))
+}
+
+/// Fails unless the player accepts the scheme of `uri`
+pub fn check_scheme(uri: &str, supported: &[String]) -> Result<()> {
+    let Some(scheme) = scheme(uri) else {
+        bail!(Message::new("open.not_uri").arg("uri", uri));
+    };
+    if supported.is_empty() {
+        bail!(Message::new("open.no_uris"));
+    }
+    if !supported.iter().any(|s| s.eq_ignore_ascii_case(scheme)) {
+        bail!(Message::new("open.scheme")
+            .arg("scheme", scheme)
+            .arg("supported", supported.join(", ")));
+    }
+    Ok(())
+}
+
+/// Complete the local path being typed as far as it is unambiguous, like a
+/// shell would. Returns `None` when there is nothing to add.
+pub fn complete_path(input: &str) -> Option<String> {
+    if scheme(input).is_some() {
+        return None;
+    }
+    let (dir, prefix) = match input.rfind('/') {
+        Some(index) => input.split_at(index + 1),
+        None => ("", input),
+    };
+    let lookup = if dir.is_empty() { PathBuf::from(".") } else { expand_home(dir) };
+
+    let mut matches: Vec<(String, bool)> = fs::read_dir(lookup)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let is_dir = entry.path().is_dir();
+            Some((name, is_dir))
+        })
+        // Hidden files only when asked for
+        .filter(|(name, _)| name.starts_with(prefix) && (prefix.starts_with('.') || !name.starts_with('.')))
+        .collect();
+    matches.sort();
+
+    let completed = match matches.as_slice() {
+        [] => return None,
+        [(name, true)] => format!("{}/", name),
+        [(name, false)] => name.clone(),
+        [(first, _), rest @ ..] => {
+            let common = rest.iter().fold(first.as_str(), |common, (name, _)| {
+                let len = common
+                    .char_indices()
+                    .zip(name.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((index, c), _)| index + c.len_utf8());
+                &common[..len]
+            });
+            common.to_string()
+        }
+    };
+    let completed = format!("{}{}", dir, completed);
+    (completed != input).then_some(completed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uris_are_kept_and_paths_become_file_uris() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("my song.mp3");
+        fs::write(&file, b"").unwrap();
+
+        assert_eq!(to_uri(" https://example.com/a.mp3 ").unwrap(), "https://example.com/a.mp3");
+        let uri = to_uri(file.to_str().unwrap()).unwrap();
+        assert!(uri.starts_with("file:///"));
+        assert!(uri.ends_with("/my%20song.mp3"));
+        assert!(to_uri(dir.path().join("missing.mp3").to_str().unwrap()).is_err());
+    }
+
+    #[test]
+    fn checks_supported_schemes() {
+        let supported = vec!["file".to_string(), "http".to_string()];
+        assert!(check_scheme("file:///tmp/a.mp3", &supported).is_ok());
+        assert!(check_scheme("HTTP://example.com", &supported).is_ok());
+        assert!(check_scheme("spotify:track:1", &supported).is_err());
+        assert!(check_scheme("file:///tmp/a.mp3", &[]).is_err());
+    }
+
+    #[test]
+    fn completes_local_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("podcasts")).unwrap();
+        fs::write(dir.path().join("song-one.mp3"), b"").unwrap();
+        fs::write(dir.path().join("song-two.mp3"), b"").unwrap();
+        let base = format!("{}/", dir.path().display());
+
+        assert_eq!(complete_path(&format!("{}pod", base)), Some(format!("{}podcasts/", base)));
+        assert_eq!(complete_path(&format!("{}so", base)), Some(format!("{}song-", base)));
+        assert_eq!(complete_path(&format!("{}song-", base)), None);
+        assert_eq!(complete_path(&format!("{}song-t", base)), Some(format!("{}song-two.mp3", base)));
+        assert_eq!(complete_path("https://exa"), None);
+    }
+}